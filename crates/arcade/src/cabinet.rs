@@ -1,4 +1,4 @@
-use atlas::TextureAtlas;
+use atlas::{AtlasError, registry::{AtlasHandle, AtlasRegistry}};
 use macroquad::{texture::{load_texture, Texture2D, draw_texture}, prelude::{WHITE, is_key_down}, input::KeyCode};
 
 
@@ -27,7 +27,8 @@ struct ButtonState(usize);
 pub struct Cabinet {
 
     cabinet_texture: Texture2D,
-    joystick_atlas: TextureAtlas,
+    joystick_frames: Vec<AtlasHandle>,
+    button_frames: Vec<AtlasHandle>,
     joystick_state: JoystickState,
     blue_button_state: ButtonState,
     red_button_state: ButtonState,
@@ -35,18 +36,22 @@ pub struct Cabinet {
 
 
 impl Cabinet {
-    pub async fn new() -> Self {
-        let cabinet_texture = load_texture("assets/cabinet/arcade_cabinet.png").await.unwrap();
+    pub async fn new(registry: &mut AtlasRegistry) -> Result<Self, AtlasError> {
+        let cabinet_texture = load_texture("assets/cabinet/arcade_cabinet.png").await
+            .map_err(|_| AtlasError::MissingFile("assets/cabinet/arcade_cabinet.png".to_owned()))?;
 
-        let joystick_atlas = TextureAtlas::from_data("assets/atlas/joystick_buttons.json", Some("assets/atlas/joystick_buttons.png")).await.unwrap();
+        registry.load("cabinet", "assets/atlas/joystick_buttons.json").await?;
+        let joystick_frames = registry.get_namespace_handles("cabinet", JOYSTICK_FRAMES)?;
+        let button_frames = registry.get_namespace_handles("cabinet", BUTTON_FRAMES)?;
 
-        Cabinet {
+        Ok(Cabinet {
             cabinet_texture,
-            joystick_atlas,
+            joystick_frames,
+            button_frames,
             joystick_state: JoystickState(4),
             blue_button_state: ButtonState(0),
             red_button_state: ButtonState(2),
-        }
+        })
     }
 
     pub fn update(&mut self) {
@@ -85,10 +90,10 @@ impl Cabinet {
 
     }
 
-    pub fn draw(&self) {
+    pub fn draw(&self, registry: &AtlasRegistry) {
         draw_texture(self.cabinet_texture, 0.0, 0.0, WHITE);
-        self.joystick_atlas.draw_texture(JOYSTICK_FRAMES[self.joystick_state.0], 16., 536., 0., WHITE);
-        self.joystick_atlas.draw_texture(BUTTON_FRAMES[self.blue_button_state.0], 160., 573., 0., WHITE);
-        self.joystick_atlas.draw_texture(BUTTON_FRAMES[self.red_button_state.0], 160., 573., 0., WHITE);
+        registry.draw_handle(self.joystick_frames[self.joystick_state.0], 16., 536., 0., WHITE);
+        registry.draw_handle(self.button_frames[self.blue_button_state.0], 160., 573., 0., WHITE);
+        registry.draw_handle(self.button_frames[self.red_button_state.0], 160., 573., 0., WHITE);
     }
 }
\ No newline at end of file
@@ -3,36 +3,45 @@ mod cabinet;
 mod wave_function_arcade;
 
 use cabinet::Cabinet;
-use wave_function_arcade::{TileSet, Arcade};
-use atlas::TextureAtlas;
-use camera_layer::CameraLayer;
+use wave_function_arcade::Arcade;
+use atlas::registry::AtlasRegistry;
+use camera_layer::{CameraLayer, ScalingMode, embed::LayerQuad, follow::CameraFollow, post_process::PostProcess, stack::LayerStack};
 use player::Player;
+use wfc::{field::WaveFunctionField, tileset::WaveFunctionTileset};
 
 use macroquad::{
     window::{
         Conf, 
-        next_frame, 
-        clear_background
+        next_frame
     }, 
     prelude::{
         is_quit_requested, 
-        set_default_camera, 
-        set_camera, 
         is_key_down, 
+        mouse_position, 
         KeyCode, 
         Vec2, 
+        Rect, 
         BLACK
     }, 
+    time::get_frame_time,
     audio::{
         load_sound, 
         play_sound, 
         PlaySoundParams
-    }
+    },
+    logging::error,
+    miniquad::date
 };
 
+const TILESET_PATH: &str = "assets/arcade_tiles/simple_area.json";
+const CRT_PATH: &str = "assets/post_process/crt.json";
+
 const WIDTH: i32 = 480;
 const HEIGHT: i32 = 640;
 
+// The hole in the cabinet art the game shows through
+const SCREEN_RECT: Rect = Rect { x: 32.0, y: 39.0, w: 416.0, h: 496.0 };
+
 #[cfg(debug_assertions)]
 const DEBUG_SCREEN_SCALE: i32 = 2;
 #[cfg(not(debug_assertions))]
@@ -50,6 +59,43 @@ fn should_exit() -> bool {
     false
 }
 
+#[cfg(debug_assertions)]
+fn draw_debug_stats(arcade: &Arcade, cursor: Option<Vec2>) {
+    use macroquad::{prelude::WHITE, text::draw_text};
+
+    let stats = arcade.get_stats();
+    let lines = [
+        format!("sectors: {} loaded, {} pending, {} persisted", stats.loaded_sectors, stats.pending_sectors, stats.persisted_sectors),
+        format!("memory: {} KiB loaded, {} KiB persisted", stats.loaded_bytes / 1024, stats.persisted_bytes / 1024),
+        format!("generated: {} restored: {} evicted: {}", stats.generated_total, stats.restored_total, stats.evicted_total),
+        match cursor {
+            Some(cursor) => format!("cursor: {:.0}, {:.0}", cursor.x, cursor.y),
+            None => "cursor: off screen".to_owned(),
+        },
+    ];
+
+    for (index, line) in lines.iter().enumerate() {
+        draw_text(line, 8.0, 20.0 + index as f32 * 18.0, 18.0, WHITE);
+    }
+}
+#[cfg(not(debug_assertions))]
+fn draw_debug_stats(_arcade: &Arcade, _cursor: Option<Vec2>) {
+}
+
+fn draw_reload_errors(arcade: &Arcade) {
+    use macroquad::{prelude::RED, text::draw_text};
+
+    let errors = arcade.get_reload_errors();
+    if errors.is_empty() {
+        return;
+    }
+
+    draw_text("Tileset reload failed:", 8.0, 80.0, 18.0, RED);
+    for (index, error) in errors.iter().enumerate() {
+        draw_text(error, 8.0, 98.0 + index as f32 * 18.0, 18.0, RED);
+    }
+}
+
 fn window_conf() -> Conf {
     Conf {
         window_title: "DUNBARCADE".to_string(),
@@ -66,15 +112,38 @@ fn window_conf() -> Conf {
 
 async fn main() {
 
-    let cabinet_layer = CameraLayer::new(WIDTH as f32, HEIGHT as f32);
-    let mut cabinet = Cabinet::new().await;
+    // Whole pixel scaling keeps the pixel art from shimmering, with black bars when the window isn't a multiple
+    let mut cabinet_layer = CameraLayer::new(WIDTH as f32, HEIGHT as f32);
+    cabinet_layer.set_scaling_mode(ScalingMode::Integer);
+
+    // Every atlas goes through the registry so each is only loaded once, whoever asks for it
+    let mut registry = AtlasRegistry::new();
+    let mut cabinet = match Cabinet::new(&mut registry).await {
+        Ok(cabinet) => cabinet,
+        Err(err) => {
+            error!("Unable to load the cabinet: {}", err);
+            return;
+        }
+    };
 
     // Offset the camera so that the target is in the center of the viewport
-    let mut arcade_layer = CameraLayer::new_with_offset(WIDTH as f32, HEIGHT as f32, Vec2::new(1.0, 1.0));
+    let mut arcade_layer = CameraLayer::new_with_offset(SCREEN_RECT.w, SCREEN_RECT.h, Vec2::new(1.0, 1.0));
+    arcade_layer.set_scaling_mode(ScalingMode::Integer);
 
-    let atlas = TextureAtlas::from_data("assets/atlas/arcade_basic.json", Some("assets/atlas/arcade_basic.png")).await.unwrap();
+    if let Err(err) = registry.load("arcade", "assets/atlas/arcade_basic.json").await {
+        error!("Unable to load the arcade atlas: {}", err);
+        return;
+    }
 
-    let tile_set = TileSet::from_basic(atlas, Vec2::new(32.0, 32.0));
+    // Checked the same way as a reload, so a broken tileset is reported rather than half built
+    let tileset = match WaveFunctionTileset::from_file(TILESET_PATH).await {
+        Ok(tileset) => tileset,
+        Err(errors) => {
+            error!("Unable to load the tileset {}: {}", TILESET_PATH, errors.join(", "));
+            return;
+        }
+    };
+    let field = WaveFunctionField::new_with_seed(tileset, date::now().to_bits());
 
     let music = load_sound("assets/audio/music/secret_of_tiki_island.ogg").await.unwrap();
     play_sound(music, PlaySoundParams {
@@ -83,57 +152,80 @@ async fn main() {
     });
 
 
-    let arcade = Arcade::new();
+    let mut arcade = match Arcade::new(field, &registry, "arcade", Vec2::new(32.0, 32.0)) {
+        Ok(arcade) => arcade,
+        Err(err) => {
+            error!("The tileset doesn't match the arcade atlas: {}", err);
+            return;
+        }
+    };
+    arcade.watch_tileset(TILESET_PATH);
+
+    let mut player = match Player::new(&mut registry).await {
+        Ok(player) => player,
+        Err(err) => {
+            error!("Unable to load the player: {}", err);
+            return;
+        }
+    };
 
-    arcade_layer.translate(8.0 * 32.0, 8.0 * 32.0);
+    player.position = Vec2::new(8.0 * 32.0, 8.0 * 32.0);
 
-    let mut player = Player::new().await;
+    // The player can wander 96 pixels from the sides and 120 from the top and bottom before the camera follows
+    let mut follow = CameraFollow::new(Rect::new(-112.0, -128.0, 224.0, 256.0));
+    follow.set_smoothing(8.0);
+    follow.set_lookahead(0.25);
+    follow.set_pixel_snap(true);
+    arcade_layer.set_follow(Some(follow));
+    arcade_layer.center_on(player.position);
+
+    // The game is shown on a CRT, it's only for looks so carry on without it if it doesn't load
+    match PostProcess::from_file(CRT_PATH).await {
+        Ok(crt) => arcade_layer.set_post_process(Some(crt)),
+        Err(err) => error!("Unable to load the CRT effect: {}", err),
+    }
 
-    player.position = Vec2::new(8.0 * 32.0, 8.0 * 32.0);
+    // The arcade is drawn into the cabinet's screen, under the bezel
+    let mut layers = LayerStack::new();
+    let arcade_layer = layers.add(arcade_layer, 0);
+    let cabinet_layer = layers.add(cabinet_layer, 1);
+    layers.set_master(Some(arcade_layer));
+    layers.set_clear_color(arcade_layer, Some(BLACK));
+    layers.set_embed(arcade_layer, Some((cabinet_layer, LayerQuad::from_rect(SCREEN_RECT))));
 
     loop {
 
         cabinet.update();
 
-        player.update();
-
-        let player_screen_pos = arcade_layer.world_to_screen(player.position);
-        let safe_min = Vec2::new(128.0 / WIDTH as f32 * WINDOW_WIDTH as f32, 192.0 / HEIGHT as f32 * WINDOW_HEIGHT as f32);
-        let safe_max = Vec2::new(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32) - safe_min;
-
-        let player_min_delta = player_screen_pos - safe_min;
-        let player_max_delta = player_screen_pos - safe_max;
+        let previous_position = player.position;
+        player.update(&registry);
 
-        if player_min_delta.x < 0.0 || player_max_delta.x > 0.0 {
-            arcade_layer.translate(player.velocity.x, 0.);
-        }
-        if player_min_delta.y < 0.0 || player_max_delta.y > 0.0 {
-            arcade_layer.translate(0., player.velocity.y);
+        if !arcade.is_walkable(player.position) {
+            player.position = previous_position;
+            player.velocity = Vec2::ZERO;
         }
 
-        set_camera(&arcade_layer.camera);
+        arcade.update(&registry, player.position);
 
-        clear_background(BLACK);
+        // The player's velocity is how far it moved this frame, the camera wants it per second
+        let frame_time = get_frame_time();
+        let player_velocity = if frame_time > 0.0 { player.velocity / frame_time } else { Vec2::ZERO };
+        let camera = layers.get_layer_mut(arcade_layer);
+        camera.update_follow(player.position, player_velocity, frame_time);
+        camera.update_effects(frame_time);
 
-        for x in -1..=1 {
-            for y in -1..=1 {
-                if let Some(sector) = arcade.sectors.get(x, y) {
-                    sector.draw(&tile_set);
-                }
+        layers.draw(|layer, _| {
+            if layer == arcade_layer {
+                arcade.draw(&registry);
+                player.draw(&registry);
             }
-        }
-
-        player.draw();
-
-        set_camera(&cabinet_layer.camera);
-
-        cabinet.draw();
-
-        set_default_camera();
-
+            else if layer == cabinet_layer {
+                cabinet.draw(&registry);
+            }
+        });
 
-        arcade_layer.draw();
-        cabinet_layer.draw();
+        draw_debug_stats(&arcade, layers.screen_to_world(arcade_layer, mouse_position().into()));
+        draw_reload_errors(&arcade);
 
         if should_exit() {
             break;
@@ -1,30 +1,11 @@
-use std::collections::HashMap;
-
-use atlas::{TextureAtlas, AtlasTextureParams};
+use atlas::{AtlasError, AtlasTextureParams, animation::AnimationPlayer, registry::AtlasRegistry};
 use macroquad::{time::get_frame_time, prelude::{Vec2, WHITE, is_key_down, KeyCode}};
 
-#[derive(Debug, Hash, PartialEq, Eq)]
-pub enum PlayerAnimation {
-    MoveDownRight,
-    MoveDownLeft,
-    MoveUpRight,
-    MoveUpLeft,
-    StoppedLeft,
-    StoppedRight,
-}
-
-pub struct AnimDef {
-    frames: Vec<String>,
-    flip_x: bool,
-}
+const ATLAS_NAMESPACE: &str = "player";
 
 pub struct Player {
-    atlas: TextureAtlas,
-    animations: HashMap<PlayerAnimation, AnimDef>,
-    animation: PlayerAnimation,
-    frame: usize,
-    anim_speed: f32,
-    frame_counter: f32,
+    animation: AnimationPlayer,
+    facing_left: bool,
     move_speed: f32, // Pixels per second
 
     pub position: Vec2,
@@ -32,79 +13,24 @@ pub struct Player {
 }
 
 impl Player {
-    pub async fn new() -> Self {
-        let atlas = TextureAtlas::from_data("assets/atlas/player.json", Some("assets/atlas/player.png")).await.unwrap();
-
-        let mut animations = HashMap::new();
-
-        animations.insert(PlayerAnimation::MoveDownRight, AnimDef {
-            frames: vec![
-                "player_down_0.png".to_string(),
-                "player_down_1.png".to_string(),
-                "player_down_2.png".to_string(),
-                "player_down_3.png".to_string(),
-            ],
-            flip_x: false
-        });
-
-        animations.insert(PlayerAnimation::MoveDownLeft, AnimDef {
-            frames: vec![
-                "player_down_0.png".to_string(),
-                "player_down_1.png".to_string(),
-                "player_down_2.png".to_string(),
-                "player_down_3.png".to_string(),
-            ],
-            flip_x: true
-        });
-
-        animations.insert(PlayerAnimation::MoveUpRight, AnimDef {
-            frames: vec![
-                "player_up_0.png".to_string(),
-                "player_up_1.png".to_string(),
-                "player_up_2.png".to_string(),
-                "player_up_3.png".to_string(),
-            ],
-            flip_x: false
-        });
-
-        animations.insert(PlayerAnimation::MoveUpLeft, AnimDef {
-            frames: vec![
-                "player_up_0.png".to_string(),
-                "player_up_1.png".to_string(),
-                "player_up_2.png".to_string(),
-                "player_up_3.png".to_string(),
-            ],
-            flip_x: true
-        });
-
-        animations.insert(PlayerAnimation::StoppedRight, AnimDef {
-            frames: vec![
-                "player_down_0.png".to_string(),
-            ],
-            flip_x: false
-        });
-
-        animations.insert(PlayerAnimation::StoppedLeft, AnimDef {
-            frames: vec![
-                "player_down_0.png".to_string(),
-            ],
-            flip_x: true
-        });
-
-        Player {
-            atlas,
-            animations,
-            animation: PlayerAnimation::MoveDownLeft,
-            frame: 0,
-            anim_speed: 1.0 / 12.0,
-            frame_counter: 0.0,
+    pub async fn new(registry: &mut AtlasRegistry) -> Result<Self, AtlasError> {
+        // The clips live on the atlas itself, so they're shared with anything else drawing the player
+        let atlas = registry.load(ATLAS_NAMESPACE, "assets/atlas/player.json").await?;
+        atlas.load_animations("assets/atlas/player_animations.json").await?;
+
+        let mut animation = AnimationPlayer::new();
+        animation.play(atlas, "stopped_left");
+
+        Ok(Player {
+            animation,
+            facing_left: true,
             move_speed: 96.0, // Pixels per second
             position: Vec2::new(0.0, 0.0),
             velocity: Vec2::new(0.0, 0.0),
-        }
+        })
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, registry: &AtlasRegistry) {
 
         let frame_time = get_frame_time();
 
@@ -131,69 +57,34 @@ impl Player {
         move_dir *= self.move_speed * frame_time;
         self.velocity = move_dir;
 
-        let target_anim_state = match (move_dir.x, move_dir.y) {
-            (x, y) if x > 0.0 && y >= 0.0 => {PlayerAnimation::MoveDownRight},
-            (x, y) if x == 0.0 && y > 0.0 => {
-                if self.animation == PlayerAnimation::MoveDownLeft || self.animation == PlayerAnimation::StoppedLeft {
-                    PlayerAnimation::MoveDownLeft
-                } else {
-                    PlayerAnimation::MoveDownRight
-                }
-            },
-            (x, y) if x < 0.0 && y >= 0.0 => {PlayerAnimation::MoveDownLeft},
-            (x, y) if x > 0.0 && y < 0.0 => {PlayerAnimation::MoveUpRight},
-            (x, y) if x == 0.0 && y < 0.0 => {
-                if self.animation == PlayerAnimation::MoveUpLeft {
-                    PlayerAnimation::MoveUpLeft
-                } else {
-                    PlayerAnimation::MoveUpRight
-                }
-            },
-            (x, y) if x <= 0.0 && y < 0.0 => {PlayerAnimation::MoveUpLeft},
-            _ => {
-                match self.animation {
-                    PlayerAnimation::MoveDownLeft => PlayerAnimation::StoppedLeft,
-                    PlayerAnimation::MoveUpLeft => PlayerAnimation::StoppedLeft,
-                    PlayerAnimation::StoppedLeft => PlayerAnimation::StoppedLeft,
-                    _ => PlayerAnimation::StoppedRight,
-                }
-            }
+        // Moving straight up or down keeps facing whichever way we last moved sideways
+        if move_dir.x != 0.0 {
+            self.facing_left = move_dir.x < 0.0;
+        }
+
+        let clip = match (move_dir.y < 0.0, move_dir == Vec2::ZERO, self.facing_left) {
+            (_, true, true) => "stopped_left",
+            (_, true, false) => "stopped_right",
+            (true, false, true) => "move_up_left",
+            (true, false, false) => "move_up_right",
+            (false, false, true) => "move_down_left",
+            (false, false, false) => "move_down_right",
         };
 
         self.position += move_dir;
         self.position.round();
 
-        if target_anim_state != self.animation {
-            self.animation = target_anim_state;
-            self.frame = 0;
+        if let Some(atlas) = registry.find_clip_page(ATLAS_NAMESPACE, clip) {
+            self.animation.play(atlas, clip);
         }
-
-        // Update the animation based on the current state
-        let current_animation = self.animations.get(&self.animation);
-        if let Some(anim_def) = current_animation {
-            self.frame_counter -= frame_time;
-
-            if self.frame_counter <= 0.0 {
-                self.frame += 1;
-
-                if self.frame >= anim_def.frames.len() {
-                    self.frame = 0;
-                }
-
-                self.frame_counter = self.anim_speed;
-            }
-        }
-
+        self.animation.update(frame_time);
     }
 
-    pub fn draw(&self) {
-
-        if let Some(anim_def) = self.animations.get(&self.animation) {
-            self.atlas.draw_texture_params(&anim_def.frames[self.frame], self.position.x, self.position.y, WHITE, AtlasTextureParams {
-                flip_x: anim_def.flip_x,
-                ..Default::default()
-            })
+    pub fn draw(&self, registry: &AtlasRegistry) {
+        // The player's frame handles belong to the page its clip came from
+        let page = self.animation.get_clip_name().and_then(|clip| registry.find_clip_page(ATLAS_NAMESPACE, clip));
+        if let Some(atlas) = page {
+            self.animation.draw(atlas, self.position.x, self.position.y, WHITE, AtlasTextureParams::default());
         }
-
     }
 }
\ No newline at end of file
@@ -1,224 +1,134 @@
-use atlas::TextureAtlas;
-use macroquad::prelude::{Vec2, WHITE};
+use atlas::{AtlasError, batch::SpriteBatch, registry::{AtlasHandle, AtlasRegistry}};
+use macroquad::{logging::info, prelude::{Vec2, WHITE}};
 
-use utilities::infinite_grid::InfiniteGrid;
+use wfc::{field::{WaveFunctionField, WaveFunctionFieldStats}, property::TileProperty, tileset::WaveFunctionTileset, watcher::TilesetWatcher};
 
-const DEG_TO_RAD: f32 = 3.14159 / 180.;
-const ROT_90: f32 = 90. * DEG_TO_RAD;
-const ROT_180: f32 = 180. * DEG_TO_RAD;
-const ROT_270: f32 = 270. * DEG_TO_RAD;
+/// Look up every texture in the tileset within an atlas namespace
+fn get_tile_frames(registry: &AtlasRegistry, namespace: &str, tileset: &WaveFunctionTileset) -> Result<Vec<AtlasHandle>, AtlasError> {
+    registry.get_namespace_handles(namespace, tileset.get_texture_ids())
+}
+
+pub struct Arcade {
+    field: WaveFunctionField,
+    atlas_namespace: String,
 
-pub struct TileSet {
-    tile_info: Vec<TileInfo>,
-    pub atlas: TextureAtlas,
+    // Atlas frames for the tileset's textures, indexed by texture handle
+    tile_frames: Vec<AtlasHandle>,
     tile_size: Vec2,
+    batch: SpriteBatch,
+
+    tileset_watcher: Option<TilesetWatcher>,
+    reload_errors: Vec<String>,
 }
 
-impl TileSet {
-    pub fn from_basic(atlas: TextureAtlas, tile_size: Vec2) -> Self {
-        let tile_vec = [
-            // Tile Index 0 - Carpet
-            TileInfo {
-                texture_id: "arcade_basic_carpet.png".to_owned(),
-                rotation: 0.
-            },
-            // Tile Index 1 - Floor Straight X
-            TileInfo {
-                texture_id: "arcade_basic_floor_straight.png".to_owned(),
-                rotation: 0.
-            },
-            // Tile Index 2 - Floor Straight Y
-            TileInfo {
-                texture_id: "arcade_basic_floor_straight.png".to_owned(),
-                rotation: ROT_90
-            },
-            // Tile Index 3 - Corner BR
-            TileInfo {
-                texture_id: "arcade_basic_floor_corner.png".to_owned(),
-                rotation: 0.
-            },
-            // Tile Index 4 - Corner BL
-            TileInfo {
-                texture_id: "arcade_basic_floor_corner.png".to_owned(),
-                rotation: ROT_90
-            },
-            // Tile Index 5 - Corner TL
-            TileInfo {
-                texture_id: "arcade_basic_floor_corner.png".to_owned(),
-                rotation: ROT_180
-            },
-            // Tile Index 6 - Corner TR
-            TileInfo {
-                texture_id: "arcade_basic_floor_corner.png".to_owned(),
-                rotation: ROT_270
-            },
-            // Tile Index 7 - TNT
-            TileInfo {
-                texture_id: "arcade_basic_floor_t.png".to_owned(),
-                rotation: 0.
-            },
-            // Tile Index 8 - TNR
-            TileInfo {
-                texture_id: "arcade_basic_floor_t.png".to_owned(),
-                rotation: ROT_90
-            },
-            // Tile Index 9 - TNB
-            TileInfo {
-                texture_id: "arcade_basic_floor_t.png".to_owned(),
-                rotation: ROT_180
-            },
-            // Tile Index 10 - TNL
-            TileInfo {
-                texture_id: "arcade_basic_floor_t.png".to_owned(),
-                rotation: ROT_270
-            },
-            // Tile Index 11 - Cross
-            TileInfo {
-                texture_id: "arcade_basic_floor_cross.png".to_owned(),
-                rotation: 0.
-            },
-        ];
-
-        TileSet {
-            tile_info: tile_vec.to_vec(),
-            atlas,
+impl Arcade {
+    /// Fails if the tileset refers to textures the atlas namespace doesn't have
+    pub fn new(field: WaveFunctionField, registry: &AtlasRegistry, atlas_namespace: &str, tile_size: Vec2) -> Result<Self, AtlasError> {
+        let tile_frames = get_tile_frames(registry, atlas_namespace, field.get_tileset())?;
+
+        Ok(Arcade {
+            field,
+            atlas_namespace: atlas_namespace.to_owned(),
+            tile_frames,
             tile_size,
-        }
+            batch: SpriteBatch::new(),
+            tileset_watcher: None,
+            reload_errors: Vec::new(),
+        })
     }
-}
 
-#[derive(Clone, PartialEq)]
-pub struct TileInfo {
-    texture_id: String,
-    rotation: f32,
-}
+    /// Rebuild the tileset and regenerate the visible sectors whenever the file at this path changes
+    pub fn watch_tileset(&mut self, path: &str) {
+        self.tileset_watcher = Some(TilesetWatcher::new(path));
+    }
 
-pub struct ArcadeSector {
-    width: u32,
-    height: u32,
-    position: Vec2,
+    fn reload_tileset(&mut self, registry: &AtlasRegistry) {
+        if let Some(watcher) = self.tileset_watcher.as_mut() {
+            match watcher.poll() {
+                Some(Ok(tileset)) => {
+                    // Keep the old tileset rather than draw holes where textures are missing
+                    match get_tile_frames(registry, &self.atlas_namespace, &tileset) {
+                        Ok(tile_frames) => self.tile_frames = tile_frames,
+                        Err(err) => {
+                            self.reload_errors = vec![err.to_string()];
+                            return;
+                        }
+                    }
+
+                    info!("Reloaded tileset {}", watcher.get_path());
+                    self.reload_errors.clear();
+
+                    // Swap the whole view over in one frame rather than letting sectors trickle back in
+                    self.field.set_tileset(tileset);
+                    self.field.wait_for_pending();
+                },
+                Some(Err(errors)) => {
+                    self.reload_errors = errors;
+                },
+                None => {}
+            }
+        }
+    }
 
-    tiles: Vec<usize>,
-}
+    /// Problems found the last time the watched tileset changed, empty once it loads cleanly
+    pub fn get_reload_errors(&self) -> &[String] {
+        &self.reload_errors
+    }
+
+    /// Stream sectors in and out around a world position, usually the player, and pick up any newly generated ones
+    pub fn update(&mut self, registry: &AtlasRegistry, focus: Vec2) {
+        self.reload_tileset(registry);
 
-impl ArcadeSector {
+        let (cell_x, cell_y) = self.get_cell(focus);
+        let ((sector_x, sector_y), _) = self.field.get_sector_coords(cell_x, cell_y);
 
-    pub fn from_indices(width: u32, height: u32, position: Vec2, indices: &[usize]) -> Self {
-        ArcadeSector { width, height, position, tiles: indices.to_vec() }
+        self.field.stream_around(sector_x, sector_y);
+        self.field.update();
     }
 
-    pub fn draw(&self, tile_set: &TileSet) {
+    fn get_cell(&self, position: Vec2) -> (i32, i32) {
+        let cell = (position / self.tile_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
 
-        for (tile_index, tile_info_handle) in self.tiles.iter().enumerate() {
-            let (x, y) = (tile_index as u32 % self.width, tile_index as u32 / self.height);
-            let (tile_x, tile_y) = ((x as f32 * tile_set.tile_size.x) + self.position.x, (y as f32 * tile_set.tile_size.y) + self.position.y);
+    /// Look up a property of the tile under a world position, if that part of the field is loaded
+    pub fn get_tile_property(&self, position: Vec2, key: &str) -> Option<&TileProperty> {
+        let (cell_x, cell_y) = self.get_cell(position);
+        self.field.get_cell_property(cell_x, cell_y, key)
+    }
 
-            let info = &tile_set.tile_info[*tile_info_handle];
-            tile_set.atlas.draw_texture(&info.texture_id, tile_x, tile_y, info.rotation, WHITE);
+    /// Cells are walkable unless their tile says otherwise. Cells whose sector hasn't loaded yet are blocked.
+    pub fn is_walkable(&self, position: Vec2) -> bool {
+        let (cell_x, cell_y) = self.get_cell(position);
+        if self.field.get_cell(cell_x, cell_y).is_none() {
+            return false;
         }
 
+        self.get_tile_property(position, "walkable").and_then(TileProperty::as_bool).unwrap_or(true)
     }
-}
 
-pub struct Arcade {
-    pub sectors: InfiniteGrid<ArcadeSector>
-}
+    pub fn draw(&mut self, registry: &AtlasRegistry) {
+        let sector_size = Vec2::new(
+            self.field.get_sector_width() as f32 * self.tile_size.x,
+            self.field.get_sector_height() as f32 * self.tile_size.y
+        );
 
-impl Arcade {
-    pub fn new() -> Self {
-
-        let mut sectors = InfiniteGrid::new();
-        sectors.set(0, 0, ArcadeSector::from_indices(16, 16, Vec2::new(0.0, 0.0), &[
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 1, 1, 1, 1, 1, 1, 4, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 3, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 11, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-        ]));
-        sectors.set(1, 0, ArcadeSector::from_indices(16, 16, Vec2::new(16. * 32., 0.0), &[
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 1, 1, 1, 1, 1, 1, 4, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 3, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 11, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-        ]));
-        sectors.set(-1, 0, ArcadeSector::from_indices(16, 16, Vec2::new(-16. * 32., 0.0), &[
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 1, 1, 1, 1, 1, 1, 4, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 3, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 11, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-        ]));
-        sectors.set(0, 1, ArcadeSector::from_indices(16, 16, Vec2::new(0.0, 16. * 32.), &[
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 1, 1, 1, 1, 1, 1, 4, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 3, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 11, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-        ]));
-        sectors.set(0, -1, ArcadeSector::from_indices(16, 16, Vec2::new(0.0, -16. * 32.), &[
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 1, 1, 1, 1, 1, 1, 4, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 3, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-            1, 1, 11, 1, 1, 1, 1, 1, 8, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0,
-        ]));
-
-        Arcade { sectors }
+        // Thousands of tiles are visible at once, so send them all in as few draw calls as possible
+        self.batch.clear();
+
+        for (sector_x, sector_y) in self.field.get_loaded_sectors() {
+            let origin = Vec2::new(sector_x as f32, sector_y as f32) * sector_size;
+
+            self.field.get_sector_texture_data(sector_x, sector_y, |x, y, (texture, rotation)| {
+                let (tile_x, tile_y) = (origin.x + x as f32 * self.tile_size.x, origin.y + y as f32 * self.tile_size.y);
+                self.batch.push_atlas_handle(registry, self.tile_frames[texture.get_index()], tile_x, tile_y, rotation.to_radians(), WHITE);
+            });
+        }
+
+        self.batch.draw();
     }
-}
\ No newline at end of file
+
+    pub fn get_stats(&self) -> WaveFunctionFieldStats {
+        self.field.get_stats()
+    }
+}
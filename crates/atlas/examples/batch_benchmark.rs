@@ -0,0 +1,77 @@
+// Compares drawing a screen full of tiles one `draw_handle` call at a time against a single `SpriteBatch`.
+// Run from the repository root so the arcade atlas can be found:
+//
+//     cargo run -p atlas --release --example batch_benchmark
+//
+// The mode switches every few seconds and the average CPU time spent submitting sprites is printed for each.
+
+use atlas::{TextureAtlas, batch::SpriteBatch};
+use macroquad::prelude::*;
+
+const TILES: [&str; 4] = [
+    "arcade_basic_floor_straight.png",
+    "arcade_basic_floor_corner.png",
+    "arcade_basic_carpet.png",
+    "arcade_basic_floor_cross.png",
+];
+
+// A 3x3 block of 16x16 sectors, what the arcade has loaded around the player
+const GRID_SIZE: usize = 48;
+const FRAMES_PER_MODE: usize = 240;
+
+#[macroquad::main("Batch benchmark")]
+async fn main() {
+    let atlas = TextureAtlas::from_data("assets/atlas/arcade_basic.json", None).await.unwrap();
+    let tiles = atlas.get_handles(TILES).unwrap();
+    let mut batch = SpriteBatch::new();
+
+    let mut batched = false;
+    let mut frame = 0;
+    let mut total = 0.0;
+
+    loop {
+        clear_background(BLACK);
+
+        let start = get_time();
+        let scale = screen_width().min(screen_height()) / (GRID_SIZE as f32 * 32.0);
+        set_camera(&Camera2D::from_display_rect(Rect::new(0., 0., screen_width() / scale, screen_height() / scale)));
+
+        if batched {
+            batch.clear();
+        }
+
+        for y in 0..GRID_SIZE {
+            for x in 0..GRID_SIZE {
+                let texture = tiles[(x * 7 + y * 3) % tiles.len()];
+                let rotation = ((x + y) % 4) as f32 * std::f32::consts::FRAC_PI_2;
+                let (tile_x, tile_y) = (x as f32 * 32.0, y as f32 * 32.0);
+
+                if batched {
+                    batch.push_handle(&atlas, texture, tile_x, tile_y, rotation, WHITE);
+                }
+                else {
+                    atlas.draw_handle(texture, tile_x, tile_y, rotation, WHITE);
+                }
+            }
+        }
+
+        if batched {
+            batch.draw();
+        }
+        total += get_time() - start;
+
+        set_default_camera();
+        let mode = if batched { "SpriteBatch" } else { "draw_handle" };
+        draw_text(&format!("{}: {} sprites, {} fps", mode, GRID_SIZE * GRID_SIZE, get_fps()), 10., 30., 30., YELLOW);
+
+        frame += 1;
+        if frame == FRAMES_PER_MODE {
+            println!("{:>12}: {:.3}ms per frame submitting {} sprites", mode, total * 1000.0 / FRAMES_PER_MODE as f64, GRID_SIZE * GRID_SIZE);
+            batched = !batched;
+            frame = 0;
+            total = 0.0;
+        }
+
+        next_frame().await
+    }
+}
@@ -0,0 +1,48 @@
+// Pack directories of loose PNGs into an atlas the game can load with `TextureAtlas::from_data`, e.g.
+//
+//     cargo run -p atlas --example pack_atlas -- assets/atlas/player.json asset_source/avatar_down asset_source/avatar_up
+//
+// The texture is written next to the JSON with the same name. Use --padding and --extrude to override the defaults.
+
+use atlas::packer::TexturePacker;
+
+fn main() -> Result<(), String> {
+    let mut args = std::env::args().skip(1);
+    let mut packer = TexturePacker::new();
+    let mut output = None;
+    let mut directories = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--padding" | "--extrude" => {
+                let value = args.next().and_then(|value| value.parse().ok()).ok_or_else(|| format!("{} needs a number", arg))?;
+                if arg == "--padding" {
+                    packer.set_padding(value);
+                }
+                else {
+                    packer.set_extrude(value);
+                }
+            },
+            _ if output.is_none() => output = Some(arg),
+            _ => directories.push(arg),
+        }
+    }
+
+    let output = output.ok_or("Usage: pack_atlas <output.json> <directory>... [--padding N] [--extrude N]")?;
+    if directories.is_empty() {
+        return Err("No directories to pack".to_owned());
+    }
+
+    for directory in directories.iter() {
+        packer.add_directory(directory)?;
+    }
+
+    let image_name = std::path::Path::new(&output).with_extension("png");
+    let image_name = image_name.file_name().and_then(|name| name.to_str()).ok_or("Invalid output path")?;
+
+    let packed = packer.pack(image_name)?;
+    packed.save(&output)?;
+
+    println!("Packed {} into {}", directories.join(", "), output);
+    Ok(())
+}
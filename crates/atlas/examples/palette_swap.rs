@@ -0,0 +1,63 @@
+// Draws the player in every outfit from one sprite sheet, and the arcade tiles under each theme.
+// Run from the repository root so the assets can be found:
+//
+//     cargo run -p atlas --example palette_swap
+//
+// Hold space to recolor the tiles by brightness instead of swapping their exact colors.
+
+use atlas::{
+    animation::AnimationPlayer,
+    batch::SpriteBatch,
+    palette::{Palette, PaletteMode},
+    AtlasTextureParams, TextureAtlas,
+};
+use macroquad::prelude::*;
+
+const TILES: [&str; 4] = [
+    "arcade_basic_floor_straight.png",
+    "arcade_basic_floor_corner.png",
+    "arcade_basic_carpet.png",
+    "arcade_basic_floor_cross.png",
+];
+
+#[macroquad::main("Palette swap")]
+async fn main() {
+    let mut player_atlas = TextureAtlas::from_data("assets/atlas/player.json", None).await.unwrap();
+    player_atlas.load_animations("assets/atlas/player_animations.json").await.unwrap();
+    let tile_atlas = TextureAtlas::from_data("assets/atlas/arcade_basic.json", None).await.unwrap();
+    let tiles = tile_atlas.get_handles(TILES).unwrap();
+
+    let outfits = Palette::from_file("assets/palettes/player_outfits.png").await.unwrap();
+    let themes = Palette::from_file("assets/palettes/arcade_themes.png").await.unwrap();
+
+    let mut animation = AnimationPlayer::new();
+    animation.play(&player_atlas, "move_down_right");
+    let mut batch = SpriteBatch::new();
+
+    loop {
+        clear_background(DARKGRAY);
+        set_camera(&Camera2D::from_display_rect(Rect::new(0., 0., 320., 240.)));
+        animation.update(get_frame_time());
+
+        // Each theme is one strip of tiles, batched and drawn with its own palette row
+        let mode = if is_key_down(KeyCode::Space) { PaletteMode::Ramp } else { PaletteMode::Swap };
+        for row in 0..themes.get_row_count() {
+            batch.clear();
+            for (index, tile) in tiles.iter().enumerate() {
+                batch.push_handle(&tile_atlas, *tile, 16. + index as f32 * 32., 16. + row as f32 * 40., 0., WHITE);
+            }
+
+            themes.begin(row, mode);
+            batch.draw();
+        }
+        themes.end();
+
+        for row in 0..outfits.get_row_count() {
+            outfits.begin(row, PaletteMode::Swap);
+            animation.draw(&player_atlas, 176. + row as f32 * 32., 32., WHITE, AtlasTextureParams::default());
+        }
+        outfits.end();
+
+        next_frame().await;
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::Color;
+use nanoserde::DeJson;
+
+use crate::{AtlasError, AtlasTextureParams, FrameHandle, TextureAtlas};
+
+// Matches the 12 fps most of our sprites are drawn at
+pub(crate) const DEFAULT_FRAME_DURATION: f32 = 1.0 / 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnimationMode {
+    /// Start over from the first frame after the last one
+    #[default]
+    Loop,
+
+    /// Run forwards then backwards, without repeating the end frames
+    PingPong,
+
+    /// Stop on the last frame
+    Once,
+}
+
+impl AnimationMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "loop" | "forward" => Some(AnimationMode::Loop),
+            "pingpong" | "ping_pong" => Some(AnimationMode::PingPong),
+            "once" => Some(AnimationMode::Once),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationFrame {
+    /// Name of the frame in the atlas
+    pub name: String,
+
+    /// How long the frame is shown for in seconds
+    pub duration: f32,
+}
+
+/// A named sequence of atlas frames
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnimationClip {
+    frames: Vec<AnimationFrame>,
+    mode: AnimationMode,
+    flip_x: bool,
+    flip_y: bool,
+}
+
+impl AnimationClip {
+    pub fn new(mode: AnimationMode) -> Self {
+        AnimationClip {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_with_frames(mode: AnimationMode, frames: Vec<AnimationFrame>) -> Self {
+        AnimationClip {
+            frames,
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// Add a frame shown for `duration` seconds
+    pub fn push_frame(&mut self, name: &str, duration: f32) {
+        self.frames.push(AnimationFrame { name: name.to_owned(), duration });
+    }
+
+    pub fn set_flip(&mut self, flip_x: bool, flip_y: bool) {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+    }
+
+    pub fn get_frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    pub fn get_frame(&self, index: usize) -> Option<&AnimationFrame> {
+        self.frames.get(index)
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn get_mode(&self) -> AnimationMode {
+        self.mode
+    }
+
+    pub fn get_flip_x(&self) -> bool {
+        self.flip_x
+    }
+
+    pub fn get_flip_y(&self) -> bool {
+        self.flip_y
+    }
+
+    /// Time taken to play every frame once, in seconds
+    pub fn get_duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+#[derive(DeJson)]
+struct ClipData {
+    frames: Vec<String>,
+
+    /// Per frame durations in milliseconds, overriding `duration`
+    durations: Option<Vec<f32>>,
+
+    /// Duration of every frame in milliseconds
+    duration: Option<f32>,
+    mode: Option<String>,
+    flip_x: Option<bool>,
+    flip_y: Option<bool>,
+}
+
+#[derive(DeJson)]
+struct AnimationData {
+    clips: HashMap<String, ClipData>,
+}
+
+/// Parse clips from a sidecar JSON file. Durations are in milliseconds to match Aseprite's exports:
+///
+/// ```json
+/// { "clips": { "walk": { "frames": ["walk_0.png", "walk_1.png"], "duration": 100, "mode": "pingpong" } } }
+/// ```
+pub fn clips_from_json(json: &str) -> Result<HashMap<String, AnimationClip>, AtlasError> {
+    let data = AnimationData::deserialize_json(json).map_err(|err| AtlasError::parse("", err))?;
+
+    let mut clips = HashMap::new();
+    for (name, clip_data) in data.clips {
+        let mode = match clip_data.mode.as_deref() {
+            Some(mode) => AnimationMode::from_name(mode).ok_or_else(|| AtlasError::invalid("", format!("Clip {} has an unknown mode {}", name, mode)))?,
+            None => AnimationMode::Loop,
+        };
+
+        if let Some(durations) = clip_data.durations.as_ref() {
+            if durations.len() != clip_data.frames.len() {
+                return Err(AtlasError::invalid("", format!("Clip {} has {} frames but {} durations", name, clip_data.frames.len(), durations.len())));
+            }
+        }
+
+        let mut clip = AnimationClip::new(mode);
+        for (index, frame) in clip_data.frames.iter().enumerate() {
+            let duration = clip_data.durations.as_ref().map(|durations| durations[index] / 1000.0)
+                .or(clip_data.duration.map(|duration| duration / 1000.0))
+                .unwrap_or(DEFAULT_FRAME_DURATION);
+
+            if duration <= 0.0 {
+                return Err(AtlasError::invalid("", format!("Frame {} of clip {} has a duration of {}", index, name, duration)));
+            }
+
+            clip.push_frame(frame, duration);
+        }
+        clip.set_flip(clip_data.flip_x.unwrap_or(false), clip_data.flip_y.unwrap_or(false));
+
+        clips.insert(name, clip);
+    }
+
+    Ok(clips)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A looping or ping-pong clip arrived back at its first frame
+    Looped(String),
+
+    /// A clip played in `Once` mode reached the end of its last frame
+    Finished(String),
+}
+
+/// Plays clips from a `TextureAtlas`, one per entity
+#[derive(Debug, Clone, Default)]
+pub struct AnimationPlayer {
+    clip_name: Option<String>,
+    clip: AnimationClip,
+
+    // The clip's frames resolved against the atlas when it started playing
+    handles: Vec<FrameHandle>,
+    frame: usize,
+    elapsed: f32,
+    reversing: bool,
+    finished: bool,
+    speed: f32,
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        AnimationPlayer {
+            speed: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// Switch to a clip from the atlas, carrying on if it's already playing.
+    /// Returns false if the atlas has no clip by that name.
+    pub fn play(&mut self, atlas: &TextureAtlas, name: &str) -> bool {
+        if self.clip_name.as_deref() == Some(name) {
+            return true;
+        }
+
+        // Clips are checked against the atlas when they're added, so their frames always resolve
+        let Some(clip) = atlas.get_clip(name) else {
+            return false;
+        };
+        let Ok(handles) = atlas.get_handles(clip.frames.iter().map(|frame| frame.name.as_str())) else {
+            return false;
+        };
+
+        self.clip_name = Some(name.to_owned());
+        self.clip = clip.clone();
+        self.handles = handles;
+        self.restart();
+        true
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.reversing = false;
+        self.finished = false;
+    }
+
+    /// Multiplier applied to the time passed to `update`
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Advance by `delta` seconds, reporting when the clip loops or finishes
+    pub fn update(&mut self, delta: f32) -> Option<AnimationEvent> {
+        let frame_count = self.clip.get_frame_count();
+        if frame_count == 0 || self.finished {
+            return None;
+        }
+
+        let mut event = None;
+        self.elapsed += delta * self.speed;
+
+        loop {
+            // Guard against zero length frames built in code spinning forever
+            let duration = self.clip.frames[self.frame].duration.max(0.001);
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+
+            let name = self.clip_name.clone().unwrap_or_default();
+            match self.clip.mode {
+                AnimationMode::Loop => {
+                    self.frame += 1;
+                    if self.frame >= frame_count {
+                        self.frame = 0;
+                        event = Some(AnimationEvent::Looped(name));
+                    }
+                },
+                AnimationMode::PingPong => {
+                    if frame_count == 1 {
+                        event = Some(AnimationEvent::Looped(name));
+                    }
+                    else if self.reversing {
+                        self.frame -= 1;
+                        if self.frame == 0 {
+                            self.reversing = false;
+                            event = Some(AnimationEvent::Looped(name));
+                        }
+                    }
+                    else {
+                        self.frame += 1;
+                        if self.frame == frame_count - 1 {
+                            self.reversing = true;
+                        }
+                    }
+                },
+                AnimationMode::Once => {
+                    if self.frame + 1 < frame_count {
+                        self.frame += 1;
+                    }
+                    else {
+                        self.elapsed = 0.0;
+                        self.finished = true;
+                        return Some(AnimationEvent::Finished(name));
+                    }
+                },
+            }
+        }
+
+        event
+    }
+
+    pub fn get_clip_name(&self) -> Option<&str> {
+        self.clip_name.as_deref()
+    }
+
+    /// Name of the atlas frame to show right now
+    pub fn get_frame_name(&self) -> Option<&str> {
+        self.clip.get_frame(self.frame).map(|frame| frame.name.as_str())
+    }
+
+    pub fn get_frame_index(&self) -> usize {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Draw the current frame, combining the clip's flip flags with the ones in `params`
+    pub fn draw(&self, atlas: &TextureAtlas, x: f32, y: f32, color: Color, params: AtlasTextureParams) {
+        if let Some(handle) = self.handles.get(self.frame) {
+            atlas.draw_handle_params(*handle, x, y, color, AtlasTextureParams {
+                flip_x: params.flip_x != self.clip.flip_x,
+                flip_y: params.flip_y != self.clip.flip_y,
+                ..params
+            });
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::{Rect, Vec2, vec2};
+use nanoserde::{DeJson, SerJson};
+
+use crate::{FrameRect, animation::{AnimationClip, AnimationMode}};
+
+// Aseprite writes durations in milliseconds, tags reference frames by their index in the export
+#[derive(DeJson, SerJson)]
+pub struct FrameTagData {
+    name: String,
+    from: usize,
+    to: usize,
+    direction: Option<String>,
+    repeat: Option<String>,
+}
+
+#[derive(DeJson, SerJson, Clone, Copy)]
+struct SlicePivot {
+    x: f32,
+    y: f32,
+}
+
+#[derive(DeJson, SerJson)]
+struct SliceKeyData {
+    frame: usize,
+    bounds: FrameRect,
+    center: Option<FrameRect>,
+    pivot: Option<SlicePivot>,
+}
+
+#[derive(DeJson, SerJson)]
+pub struct SliceData {
+    name: String,
+    keys: Vec<SliceKeyData>,
+}
+
+/// The state of a slice from a given frame onwards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SliceKey {
+    /// First frame this key applies to
+    pub frame: usize,
+
+    /// Bounds of the slice within the untrimmed sprite
+    pub bounds: Rect,
+
+    /// Nine-slice center, relative to `bounds`
+    pub center: Option<Rect>,
+
+    /// Pivot in pixels, relative to `bounds`
+    pub pivot: Option<Vec2>,
+}
+
+/// A named region drawn in Aseprite, keyed over the frames it changes on
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasSlice {
+    pub name: String,
+    pub keys: Vec<SliceKey>,
+}
+
+impl AtlasSlice {
+    /// The key in effect for a frame index, keys carry forward until the next one
+    pub fn get_key(&self, frame: usize) -> Option<&SliceKey> {
+        self.keys.iter().rev().find(|key| key.frame <= frame).or(self.keys.first())
+    }
+}
+
+fn to_rect(rect: FrameRect) -> Rect {
+    Rect::new(rect.x, rect.y, rect.w, rect.h)
+}
+
+pub(crate) fn slices_from_data(data: &[SliceData]) -> Vec<AtlasSlice> {
+    data.iter().map(|slice| {
+        let mut keys: Vec<_> = slice.keys.iter().map(|key| SliceKey {
+            frame: key.frame,
+            bounds: to_rect(key.bounds),
+            center: key.center.map(to_rect),
+            pivot: key.pivot.map(|pivot| vec2(pivot.x, pivot.y)),
+        }).collect();
+        keys.sort_by_key(|key| key.frame);
+
+        AtlasSlice { name: slice.name.clone(), keys }
+    }).collect()
+}
+
+/// Turn frame tags into clips. `frames` holds each exported frame's name and duration in seconds, in export order.
+pub(crate) fn clips_from_tags(tags: &[FrameTagData], frames: &[(&str, f32)]) -> Result<HashMap<String, AnimationClip>, String> {
+    let mut clips = HashMap::new();
+
+    for tag in tags {
+        if tag.from > tag.to || tag.to >= frames.len() {
+            return Err(format!("Frame tag {} covers frames {} to {} but there are only {} frames", tag.name, tag.from, tag.to, frames.len()));
+        }
+
+        let (mode, reverse) = match tag.direction.as_deref().unwrap_or("forward") {
+            "forward" => (AnimationMode::Loop, false),
+            "reverse" => (AnimationMode::Loop, true),
+            "pingpong" => (AnimationMode::PingPong, false),
+            "pingpong_reverse" => (AnimationMode::PingPong, true),
+            direction => return Err(format!("Frame tag {} has an unknown direction {}", tag.name, direction)),
+        };
+
+        // A tag set to play a single time in Aseprite shouldn't loop in game either
+        let mode = match tag.repeat.as_deref() {
+            Some("1") => AnimationMode::Once,
+            _ => mode,
+        };
+
+        let mut clip = AnimationClip::new(mode);
+        let mut push = |index: usize| {
+            let (name, duration) = frames[index];
+            clip.push_frame(name, duration);
+        };
+
+        if reverse {
+            (tag.from..=tag.to).rev().for_each(&mut push);
+        }
+        else {
+            (tag.from..=tag.to).for_each(&mut push);
+        }
+
+        clips.insert(tag.name.clone(), clip);
+    }
+
+    Ok(clips)
+}
@@ -0,0 +1,175 @@
+use macroquad::{
+    models::{draw_mesh, Mesh, Vertex},
+    prelude::{vec2, Color, Rect, Vec2},
+    texture::Texture2D,
+};
+
+use crate::{
+    font::{BitmapFont, TextParams},
+    registry::{AtlasHandle, AtlasRegistry},
+    AtlasError, AtlasTextureParams, FrameHandle, FrameQuad, TextureAtlas,
+};
+
+// macroquad clamps any single draw call to fewer than 5000 indices, so split meshes well before that
+const MAX_QUADS_PER_MESH: usize = 800;
+
+/// Collects atlas draws into meshes so a whole frame's worth of sprites goes out in a handful of draw calls.
+/// Clear it at the start of each frame, push sprites, then `draw` once.
+#[derive(Default)]
+pub struct SpriteBatch {
+    meshes: Vec<Mesh>,
+
+    // Meshes kept from earlier frames so clearing doesn't throw their allocations away
+    spare: Vec<Mesh>,
+    quad_count: usize,
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        for mut mesh in self.meshes.drain(..) {
+            mesh.vertices.clear();
+            mesh.indices.clear();
+            self.spare.push(mesh);
+        }
+        self.quad_count = 0;
+    }
+
+    /// Number of sprites pushed since the last clear
+    pub fn len(&self) -> usize {
+        self.quad_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quad_count == 0
+    }
+
+    /// Number of draw calls `draw` will issue
+    pub fn get_mesh_count(&self) -> usize {
+        self.meshes.len()
+    }
+
+    pub fn push_texture(&mut self, atlas: &TextureAtlas, texture: &str, x: f32, y: f32, rotation: f32, color: Color) -> Result<(), AtlasError> {
+        self.push_texture_params(atlas, texture, x, y, color, AtlasTextureParams {
+            rotation,
+            ..Default::default()
+        })
+    }
+
+    /// Queue a frame exactly as `TextureAtlas::draw_texture_params` would draw it
+    pub fn push_texture_params(&mut self, atlas: &TextureAtlas, texture: &str, x: f32, y: f32, color: Color, params: AtlasTextureParams) -> Result<(), AtlasError> {
+        let quad = atlas.get_quad(texture, x, y, &params)?;
+        self.push_quad(atlas.get_texture(), &quad, color);
+        Ok(())
+    }
+
+    pub fn push_handle(&mut self, atlas: &TextureAtlas, handle: FrameHandle, x: f32, y: f32, rotation: f32, color: Color) {
+        self.push_handle_params(atlas, handle, x, y, color, AtlasTextureParams {
+            rotation,
+            ..Default::default()
+        });
+    }
+
+    pub fn push_handle_params(&mut self, atlas: &TextureAtlas, handle: FrameHandle, x: f32, y: f32, color: Color, params: AtlasTextureParams) {
+        let quad = atlas.get_handle_quad(handle, x, y, &params);
+        self.push_quad(atlas.get_texture(), &quad, color);
+    }
+
+    /// Queue a region of a texture stretched over `dest`, for callers that cut frames up themselves
+    pub fn push_region(&mut self, texture: Texture2D, region: Rect, dest: Rect, color: Color) {
+        self.push_quad(texture, &FrameQuad {
+            region,
+            center: dest.center(),
+            size: dest.size(),
+            rotation: 0.,
+            flip_x: false,
+            flip_y: false,
+        }, color);
+    }
+
+    /// Queue a frame from a registry. Frames from different pages can't share a mesh, so group draws by page where order allows.
+    pub fn push_atlas_handle(&mut self, registry: &AtlasRegistry, handle: AtlasHandle, x: f32, y: f32, rotation: f32, color: Color) {
+        self.push_handle(registry.get_page(handle), handle.get_frame(), x, y, rotation, color);
+    }
+
+    pub fn push_atlas_handle_params(&mut self, registry: &AtlasRegistry, handle: AtlasHandle, x: f32, y: f32, color: Color, params: AtlasTextureParams) {
+        self.push_handle_params(registry.get_page(handle), handle.get_frame(), x, y, color, params);
+    }
+
+    pub fn push_nine_slice(&mut self, atlas: &TextureAtlas, name: &str, dest: Rect, color: Color) -> Result<(), AtlasError> {
+        for (region, piece) in atlas.get_nine_slice_pieces(name, dest)? {
+            self.push_region(atlas.get_texture(), region, piece, color);
+        }
+        Ok(())
+    }
+
+    /// Queue text, sharing a mesh with sprites when the font's glyphs live in the same atlas
+    pub fn push_text(&mut self, font: &BitmapFont, text: &str, x: f32, y: f32, params: &TextParams) {
+        for (region, dest) in font.layout(text, x, y, params) {
+            self.push_region(font.get_texture(), region, dest, params.color);
+        }
+    }
+
+    fn get_mesh(&mut self, texture: Texture2D) -> &mut Mesh {
+        // Start a new mesh when the texture changes or the current one is full, keeping draw order intact
+        let full = self.meshes.last().is_none_or(|mesh| {
+            mesh.texture != Some(texture) || mesh.vertices.len() >= MAX_QUADS_PER_MESH * 4
+        });
+
+        if full {
+            let mut mesh = self.spare.pop().unwrap_or(Mesh {
+                vertices: Vec::new(),
+                indices: Vec::new(),
+                texture: None,
+            });
+            mesh.texture = Some(texture);
+            self.meshes.push(mesh);
+        }
+
+        self.meshes.last_mut().unwrap()
+    }
+
+    pub(crate) fn push_quad(&mut self, texture: Texture2D, quad: &FrameQuad, color: Color) {
+        let texture_size = vec2(texture.width(), texture.height());
+        let mesh = self.get_mesh(texture);
+
+        let half = quad.size * 0.5;
+        let (sin, cos) = quad.rotation.sin_cos();
+        let corner = |offset: Vec2| quad.center + vec2(offset.x * cos - offset.y * sin, offset.x * sin + offset.y * cos);
+
+        let (mut left, mut right) = (quad.region.x / texture_size.x, (quad.region.x + quad.region.w) / texture_size.x);
+        let (mut top, mut bottom) = (quad.region.y / texture_size.y, (quad.region.y + quad.region.h) / texture_size.y);
+        if quad.flip_x {
+            std::mem::swap(&mut left, &mut right);
+        }
+        if quad.flip_y {
+            std::mem::swap(&mut top, &mut bottom);
+        }
+
+        let base = mesh.vertices.len() as u16;
+        let corners = [
+            (corner(vec2(-half.x, -half.y)), vec2(left, top)),
+            (corner(vec2(half.x, -half.y)), vec2(right, top)),
+            (corner(vec2(half.x, half.y)), vec2(right, bottom)),
+            (corner(vec2(-half.x, half.y)), vec2(left, bottom)),
+        ];
+
+        mesh.vertices.extend(corners.iter().map(|(position, uv)| Vertex {
+            position: position.extend(0.),
+            uv: *uv,
+            color,
+        }));
+        mesh.indices.extend([0, 1, 2, 0, 2, 3].iter().map(|index| base + index));
+
+        self.quad_count += 1;
+    }
+
+    pub fn draw(&self) {
+        for mesh in self.meshes.iter() {
+            draw_mesh(mesh);
+        }
+    }
+}
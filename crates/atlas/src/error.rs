@@ -0,0 +1,83 @@
+use std::fmt;
+
+use nanoserde::DeJsonErr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AtlasError {
+    /// A data file couldn't be read
+    MissingFile(String),
+
+    /// A data file isn't valid JSON or doesn't have the layout we expect
+    Parse {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    /// The texture couldn't be loaded, `image` is `None` when the data doesn't name one
+    MissingImage {
+        path: String,
+        image: Option<String>,
+    },
+
+    /// Two frames in one atlas share a name
+    DuplicateFrame {
+        path: String,
+        name: String,
+    },
+
+    /// A frame was looked up, or referred to by a clip, nine-slice or font, that isn't in the atlas
+    UnknownFrame(String),
+
+    /// The data parsed but describes something we can't use
+    Invalid {
+        path: String,
+        message: String,
+    },
+}
+
+impl AtlasError {
+    pub(crate) fn parse(path: &str, err: DeJsonErr) -> Self {
+        // nanoserde counts lines and columns from zero
+        AtlasError::Parse {
+            path: path.to_owned(),
+            line: err.line + 1,
+            column: err.col + 1,
+            message: err.msg,
+        }
+    }
+
+    pub(crate) fn invalid(path: &str, message: impl Into<String>) -> Self {
+        AtlasError::Invalid {
+            path: path.to_owned(),
+            message: message.into(),
+        }
+    }
+
+    /// Fill in the file an error came from, for errors raised while parsing data handed over as a string
+    pub(crate) fn with_path(self, path: &str) -> Self {
+        match self {
+            AtlasError::Parse { path: existing, line, column, message } if existing.is_empty() => AtlasError::Parse { path: path.to_owned(), line, column, message },
+            AtlasError::Invalid { path: existing, message } if existing.is_empty() => AtlasError::invalid(path, message),
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::MissingFile(path) => write!(f, "Unable to load {}", path),
+            AtlasError::Parse { path, line, column, message } => write!(f, "{}:{}:{}: {}", path, line, column, message),
+            AtlasError::MissingImage { path, image: Some(image) } => write!(f, "Unable to load image {} for {}", image, path),
+            AtlasError::MissingImage { path, image: None } => write!(f, "{} doesn't name an image", path),
+            AtlasError::DuplicateFrame { path, name } => write!(f, "Frame {} appears more than once in {}", name, path),
+            AtlasError::UnknownFrame(name) => write!(f, "Unknown frame {}", name),
+            AtlasError::Invalid { path, message } if path.is_empty() => write!(f, "{}", message),
+            AtlasError::Invalid { path, message } => write!(f, "{} in {}", message, path),
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use macroquad::{
+    prelude::{load_string, vec2, Color, Rect, Vec2, WHITE},
+    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
+};
+
+use crate::{AtlasError, TextureAtlas};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Glyph {
+    /// Region of the font texture
+    region: Rect,
+
+    /// Offset from the pen position to the top left of the glyph
+    offset: Vec2,
+
+    /// How far the pen moves after drawing the glyph
+    advance: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextParams {
+    pub color: Color,
+
+    /// Whole numbers keep the pixels square
+    pub scale: f32,
+    pub align: TextAlign,
+
+    /// Wrap lines at spaces to stay within this width, also used as the box lines are aligned in
+    pub max_width: Option<f32>,
+
+    /// Extra space between lines on top of the font's line height
+    pub line_spacing: f32,
+}
+
+impl Default for TextParams {
+    fn default() -> Self {
+        TextParams {
+            color: WHITE,
+            scale: 1.0,
+            align: TextAlign::Left,
+            max_width: None,
+            line_spacing: 0.0,
+        }
+    }
+}
+
+/// Pull the tag name and `key=value` attributes out of a BMFont line, or an XML element with the same attributes
+fn parse_tag(line: &str) -> Option<(&str, HashMap<&str, &str>)> {
+    let line = line.trim().trim_start_matches('<').trim_end_matches('>').trim_end_matches('/');
+    let (tag, mut rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    if tag.is_empty() {
+        return None;
+    }
+
+    let mut attributes = HashMap::new();
+    loop {
+        rest = rest.trim_start();
+        let Some((key, value)) = rest.split_once('=') else {
+            break;
+        };
+
+        // Values may be quoted to hold spaces, like face="Press Start"
+        let (value, remaining) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+        };
+
+        attributes.insert(key.trim(), value);
+        rest = remaining;
+    }
+
+    Some((tag, attributes))
+}
+
+/// Region of an atlas frame holding a font's glyphs
+fn get_sheet_region(atlas: &TextureAtlas, frame: &str) -> Result<Rect, AtlasError> {
+    let atlas_frame = atlas.get_frame(frame)?;
+
+    // Trimming or rotating the sheet would move the glyphs around
+    if atlas_frame.rotated || atlas_frame.trim.size() != atlas_frame.source_size {
+        return Err(AtlasError::invalid("", format!("Font frame {} must be packed without trimming or rotation", frame)));
+    }
+
+    Ok(atlas_frame.region)
+}
+
+/// Text drawn from glyphs in a texture, for crisp pixel-art text at any `CameraLayer` size
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    texture: Texture2D,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+    line_height: f32,
+}
+
+impl BitmapFont {
+    /// Load an AngelCode BMFont `.fnt` file in either its text or XML flavour, with its page packed into an atlas as `frame`.
+    /// Only single page fonts are supported.
+    pub async fn from_bmfont(path: &str, atlas: &TextureAtlas, frame: &str) -> Result<Self, AtlasError> {
+        let contents = load_string(path).await.map_err(|_| AtlasError::MissingFile(path.to_owned()))?;
+        Self::from_bmfont_str(&contents, atlas, frame).map_err(|err| err.with_path(path))
+    }
+
+    /// Same as `from_bmfont` for a font file that's already been read
+    pub fn from_bmfont_str(contents: &str, atlas: &TextureAtlas, frame: &str) -> Result<Self, AtlasError> {
+        let page = get_sheet_region(atlas, frame)?;
+
+        // XML fonts put everything in elements, text fonts put one tag per line
+        let tags: Vec<&str> = if contents.trim_start().starts_with('<') {
+            contents.split('<').filter(|element| !element.starts_with(['/', '?', '!'])).collect()
+        }
+        else {
+            contents.lines().collect()
+        };
+
+        let number = |attributes: &HashMap<&str, &str>, key: &str| -> Result<f32, AtlasError> {
+            attributes.get(key)
+                .ok_or_else(|| AtlasError::invalid("", format!("Font is missing {}", key)))?
+                .parse::<f32>()
+                .map_err(|_| AtlasError::invalid("", format!("Font has an invalid {}", key)))
+        };
+        let character = |id: f32| char::from_u32(id as u32).ok_or_else(|| AtlasError::invalid("", format!("Font has an invalid character id {}", id)));
+
+        let mut line_height = 0.0;
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+
+        for (tag, attributes) in tags.iter().filter_map(|tag| parse_tag(tag)) {
+            match tag {
+                "common" => {
+                    line_height = number(&attributes, "lineHeight")?;
+                    if number(&attributes, "pages")? > 1.0 {
+                        return Err(AtlasError::invalid("", "Font has more than one page"));
+                    }
+                },
+                "char" => {
+                    let character = character(number(&attributes, "id")?)?;
+                    let region = Rect::new(number(&attributes, "x")?, number(&attributes, "y")?, number(&attributes, "width")?, number(&attributes, "height")?);
+                    if region.right() > page.w || region.bottom() > page.h {
+                        return Err(AtlasError::invalid("", format!("Glyph {:?} lies outside font frame {}", character, frame)));
+                    }
+
+                    // Glyph positions are relative to the font's page, wherever it was packed
+                    glyphs.insert(character, Glyph {
+                        region: Rect::new(page.x + region.x, page.y + region.y, region.w, region.h),
+                        offset: vec2(number(&attributes, "xoffset")?, number(&attributes, "yoffset")?),
+                        advance: number(&attributes, "xadvance")?,
+                    });
+                },
+                "kerning" => {
+                    let pair = (character(number(&attributes, "first")?)?, character(number(&attributes, "second")?)?);
+                    kerning.insert(pair, number(&attributes, "amount")?);
+                },
+                _ => {}
+            }
+        }
+
+        Ok(BitmapFont {
+            texture: atlas.get_texture(),
+            glyphs,
+            kerning,
+            line_height,
+        })
+    }
+
+    /// Build a monospaced font from an atlas frame holding a grid of equally sized glyphs.
+    /// `characters` lists the glyphs left to right, top to bottom. Spaces don't need a cell of their own.
+    pub fn from_grid(atlas: &TextureAtlas, frame: &str, glyph_size: Vec2, characters: &str) -> Result<Self, AtlasError> {
+        if glyph_size.x <= 0.0 || glyph_size.y <= 0.0 {
+            return Err(AtlasError::invalid("", format!("Font frame {} has an invalid glyph size {}", frame, glyph_size)));
+        }
+
+        let region = get_sheet_region(atlas, frame)?;
+        let columns = (region.w / glyph_size.x).floor() as usize;
+        let rows = (region.h / glyph_size.y).floor() as usize;
+        let capacity = columns.saturating_mul(rows);
+        if characters.chars().count() > capacity {
+            return Err(AtlasError::invalid("", format!("Font frame {} only has room for {} glyphs", frame, capacity)));
+        }
+
+        let mut glyphs: HashMap<char, Glyph> = characters.chars().enumerate().map(|(index, character)| {
+            let (column, row) = (index % columns, index / columns);
+            (character, Glyph {
+                region: Rect::new(region.x + column as f32 * glyph_size.x, region.y + row as f32 * glyph_size.y, glyph_size.x, glyph_size.y),
+                offset: Vec2::ZERO,
+                advance: glyph_size.x,
+            })
+        }).collect();
+
+        // An empty glyph, otherwise spaces would fall back to the question mark
+        glyphs.entry(' ').or_insert(Glyph {
+            region: Rect::new(0., 0., 0., 0.),
+            offset: Vec2::ZERO,
+            advance: glyph_size.x,
+        });
+
+        Ok(BitmapFont {
+            texture: atlas.get_texture(),
+            glyphs,
+            kerning: HashMap::new(),
+            line_height: glyph_size.y,
+        })
+    }
+
+    pub fn get_texture(&self) -> Texture2D {
+        self.texture
+    }
+
+    pub fn get_line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    pub fn set_kerning(&mut self, first: char, second: char, amount: f32) {
+        self.kerning.insert((first, second), amount);
+    }
+
+    fn get_glyph(&self, character: char) -> Option<&Glyph> {
+        // Fall back to a question mark so missing glyphs are obvious rather than silently dropped
+        self.glyphs.get(&character).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn get_advance(&self, previous: Option<char>, character: char) -> f32 {
+        let kerning = previous.and_then(|previous| self.kerning.get(&(previous, character))).copied().unwrap_or(0.0);
+        self.get_glyph(character).map_or(0.0, |glyph| glyph.advance) + kerning
+    }
+
+    fn measure_line(&self, line: &str) -> f32 {
+        let mut previous = None;
+        line.chars().map(|character| {
+            let advance = self.get_advance(previous, character);
+            previous = Some(character);
+            advance
+        }).sum()
+    }
+
+    /// Break text into lines at newlines, and at spaces when a line would run past `max_width`
+    fn wrap<'a>(&self, text: &'a str, max_width: Option<f32>) -> Vec<&'a str> {
+        let mut lines = Vec::new();
+
+        for paragraph in text.split('\n') {
+            let Some(max_width) = max_width else {
+                lines.push(paragraph);
+                continue;
+            };
+
+            let mut start = 0;
+            let mut end = 0;
+            for (index, _) in paragraph.match_indices(' ').chain(std::iter::once((paragraph.len(), ""))) {
+                if end > start && self.measure_line(&paragraph[start..index]) > max_width {
+                    lines.push(&paragraph[start..end]);
+                    start = end + 1;
+                }
+                end = index;
+            }
+            lines.push(&paragraph[start..]);
+        }
+
+        lines
+    }
+
+    /// Size of the block of text once wrapped, before scaling
+    pub fn measure(&self, text: &str, max_width: Option<f32>) -> Vec2 {
+        let lines = self.wrap(text, max_width);
+        let width = lines.iter().map(|line| self.measure_line(line)).fold(0.0, f32::max);
+        vec2(width, lines.len() as f32 * self.line_height)
+    }
+
+    /// Place every glyph, returning (texture region, destination) pairs for drawing the text some other way
+    pub fn layout(&self, text: &str, x: f32, y: f32, params: &TextParams) -> Vec<(Rect, Rect)> {
+        let max_width = params.max_width.map(|width| width / params.scale);
+        let lines = self.wrap(text, max_width);
+        let widths: Vec<f32> = lines.iter().map(|line| self.measure_line(line)).collect();
+        let box_width = max_width.unwrap_or_else(|| widths.iter().copied().fold(0.0, f32::max));
+
+        let mut quads = Vec::with_capacity(text.len());
+        for (index, (line, width)) in lines.iter().zip(widths).enumerate() {
+            let mut pen = match params.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => ((box_width - width) * 0.5).floor(),
+                TextAlign::Right => box_width - width,
+            };
+            let top = index as f32 * (self.line_height + params.line_spacing / params.scale);
+
+            let mut previous = None;
+            for character in line.chars() {
+                let kerning = previous.and_then(|previous| self.kerning.get(&(previous, character))).copied().unwrap_or(0.0);
+                previous = Some(character);
+
+                if let Some(glyph) = self.get_glyph(character) {
+                    pen += kerning;
+                    if glyph.region.w > 0.0 && glyph.region.h > 0.0 {
+                        // Snap to whole pixels so glyphs don't smear between texels
+                        let dest = Rect::new(
+                            (x + (pen + glyph.offset.x) * params.scale).round(),
+                            (y + (top + glyph.offset.y) * params.scale).round(),
+                            glyph.region.w * params.scale,
+                            glyph.region.h * params.scale,
+                        );
+                        quads.push((glyph.region, dest));
+                    }
+                    pen += glyph.advance;
+                }
+            }
+        }
+        quads
+    }
+
+    /// Draw text with its top left at (x, y), or its box's top left when aligning
+    pub fn draw_text(&self, text: &str, x: f32, y: f32, params: &TextParams) {
+        for (region, dest) in self.layout(text, x, y, params) {
+            draw_texture_ex(self.texture, dest.x, dest.y, params.color, DrawTextureParams {
+                dest_size: Some(dest.size()),
+                source: Some(region),
+                ..Default::default()
+            });
+        }
+    }
+}
@@ -1,6 +1,25 @@
-use std::{collections::HashMap, path::Path};
+// The code generated by nanoserde's DeJson derive for optional fields trips this lint
+#![allow(clippy::question_mark)]
 
-use nanoserde::DeJson;
+pub mod animation;
+mod aseprite;
+pub mod piskel;
+pub mod packer;
+pub mod batch;
+pub mod nine_slice;
+pub mod font;
+pub mod registry;
+pub mod palette;
+mod error;
+
+pub use aseprite::{AtlasSlice, SliceKey};
+pub use error::AtlasError;
+
+use std::{collections::HashMap, path::Path, str::Chars};
+
+use animation::AnimationClip;
+use nine_slice::{NineSlice, NineSliceInsets};
+use nanoserde::{DeJson, DeJsonErr, DeJsonState, DeJsonTok, SerJson, SerJsonState};
 use macroquad::{
     texture::{
         Texture2D,
@@ -11,11 +30,11 @@ use macroquad::{
     prelude::{
         load_string,
         Color,
-        Rect, Vec2
+        Rect, Vec2, vec2
     },
 };
 
-#[derive(DeJson, Clone, Copy)]
+#[derive(DeJson, SerJson, Clone, Copy)]
 struct FrameRect {
     x: f32,
     y: f32,
@@ -24,14 +43,20 @@ struct FrameRect {
 }
 
 #[allow(dead_code)]
-#[derive(DeJson)]
+#[derive(DeJson, SerJson)]
 struct AtlasSize {
     w: f32,
     h: f32,
 }
 
+#[derive(DeJson, SerJson, Clone, Copy)]
+struct FramePivot {
+    x: f32,
+    y: f32,
+}
+
 #[allow(dead_code)]
-#[derive(DeJson)]
+#[derive(DeJson, SerJson)]
 struct FrameData {
     filename: Option<String>,
     frame: FrameRect,
@@ -41,10 +66,49 @@ struct FrameData {
     sprite_source_size: FrameRect,
     #[nserde(rename = "sourceSize")]
     source_size: AtlasSize,
+    pivot: Option<FramePivot>,
+
+    /// Aseprite exports how long each frame is shown for in milliseconds
+    duration: Option<f32>,
+}
+
+/// TexturePacker's json-array and Aseprite's array export list the frames, the hash exports key them by name
+struct FrameList(Vec<FrameData>);
+
+impl DeJson for FrameList {
+    fn de_json(state: &mut DeJsonState, input: &mut Chars) -> Result<Self, DeJsonErr> {
+        match state.tok {
+            DeJsonTok::BlockOpen => Ok(FrameList(Vec::de_json(state, input)?)),
+            DeJsonTok::CurlyOpen => {
+                // Read the object by hand rather than into a HashMap, frame tags refer to frames by their position
+                let mut frames = Vec::new();
+                state.curly_open(input)?;
+                while state.tok != DeJsonTok::CurlyClose {
+                    let name = String::de_json(state, input)?;
+                    state.colon(input)?;
+                    let mut frame = FrameData::de_json(state, input)?;
+                    state.eat_comma_curly(input)?;
+
+                    frame.filename = Some(name);
+                    frames.push(frame);
+                }
+                state.curly_close(input)?;
+                Ok(FrameList(frames))
+            },
+            _ => Err(state.err_token("frame array or object")),
+        }
+    }
+}
+
+// Always written as TexturePacker's array layout
+impl SerJson for FrameList {
+    fn ser_json(&self, depth: usize, state: &mut SerJsonState) {
+        self.0.ser_json(depth, state);
+    }
 }
 
 #[allow(dead_code)]
-#[derive(DeJson)]
+#[derive(DeJson, SerJson)]
 struct MetaData {
     app: Option<String>,
     version: Option<String>,
@@ -54,19 +118,197 @@ struct MetaData {
     scale: Option<String>,
     #[nserde(rename = "smartupdate")]
     smart_update: Option<String>,
+    #[nserde(rename = "frameTags")]
+    frame_tags: Option<Vec<aseprite::FrameTagData>>,
+    slices: Option<Vec<aseprite::SliceData>>,
+
+    /// The other pages of a TexturePacker multipack, relative to this file
+    related_multi_packs: Option<Vec<String>>,
 }
 
-#[derive(DeJson)]
+#[derive(DeJson, SerJson)]
 struct AtlasData {
-    frames: Vec<FrameData>,
+    frames: FrameList,
     meta: MetaData,
 }
 
-#[allow(dead_code)]
+/// Where a frame lives in the texture and how to get back to the sprite it was packed from
+#[derive(Debug, Clone, Copy)]
+struct AtlasFrame {
+    /// Region of the texture, already swapped to the packed orientation for rotated frames
+    region: Rect,
+
+    /// TexturePacker turns rotated frames 90 degrees clockwise in the sheet
+    rotated: bool,
+
+    /// Position and size of the trimmed pixels within the original sprite
+    trim: Rect,
+
+    /// Size of the original sprite before trimming
+    source_size: Vec2,
+
+    /// Normalized pivot within the original sprite
+    pivot: Vec2,
+
+    /// Seconds to show the frame for when it's part of a clip
+    duration: f32,
+}
+
+impl AtlasFrame {
+    fn from_data(data: &FrameData) -> Self {
+        let FrameRect { x, y, w, h } = data.frame;
+        let region = if data.rotated { Rect::new(x, y, h, w) } else { Rect::new(x, y, w, h) };
+
+        let trim = data.sprite_source_size;
+        let pivot = data.pivot.map_or(vec2(0.5, 0.5), |pivot| vec2(pivot.x, pivot.y));
+
+        AtlasFrame {
+            region,
+            rotated: data.rotated,
+            trim: Rect::new(trim.x, trim.y, trim.w, trim.h),
+            source_size: vec2(data.source_size.w, data.source_size.h),
+            pivot,
+            duration: data.duration.map_or(animation::DEFAULT_FRAME_DURATION, |duration| duration / 1000.0),
+        }
+    }
+
+    fn get_quad(&self, position: Vec2, params: &AtlasTextureParams) -> FrameQuad {
+        let mut trim = self.trim;
+        let mut pivot = self.pivot;
+
+        // Mirror within the untrimmed bounds so flipped sprites stay lined up with unflipped ones
+        if params.flip_x {
+            trim.x = self.source_size.x - trim.x - trim.w;
+            pivot.x = 1. - pivot.x;
+        }
+        if params.flip_y {
+            trim.y = self.source_size.y - trim.y - trim.h;
+            pivot.y = 1. - pivot.y;
+        }
+
+        let pivot = pivot * self.source_size;
+        let origin = match params.anchor {
+            AtlasAnchor::TopLeft => position,
+            AtlasAnchor::Pivot => position - pivot,
+        };
+
+        // Work out where the middle of the trimmed pixels ends up once rotated around the pivot,
+        // then spin the quad around its own center so rotated frames can be turned back upright
+        let rotation_pivot = params.pivot.unwrap_or(origin + pivot);
+        let center = origin + vec2(trim.x + trim.w * 0.5, trim.y + trim.h * 0.5) - rotation_pivot;
+        let (sin, cos) = params.rotation.sin_cos();
+        let center = rotation_pivot + vec2(center.x * cos - center.y * sin, center.x * sin + center.y * cos);
+
+        if self.rotated {
+            // The sprite's X axis runs down the packed region, so the flips swap over too
+            FrameQuad {
+                region: self.region,
+                center,
+                size: vec2(trim.h, trim.w),
+                rotation: params.rotation - std::f32::consts::FRAC_PI_2,
+                flip_x: params.flip_y,
+                flip_y: params.flip_x,
+            }
+        }
+        else {
+            FrameQuad {
+                region: self.region,
+                center,
+                size: vec2(trim.w, trim.h),
+                rotation: params.rotation,
+                flip_x: params.flip_x,
+                flip_y: params.flip_y,
+            }
+        }
+    }
+
+    /// A frame packed as is, without trimming or rotation
+    fn new_untrimmed(region: Rect, duration: f32) -> Self {
+        AtlasFrame {
+            region,
+            rotated: false,
+            trim: Rect::new(0., 0., region.w, region.h),
+            source_size: region.size(),
+            pivot: vec2(0.5, 0.5),
+            duration,
+        }
+    }
+}
+
+/// A region of the atlas texture placed on screen, rotated around its own center
+#[derive(Debug, Clone, Copy)]
+pub struct FrameQuad {
+    pub region: Rect,
+    pub center: Vec2,
+    pub size: Vec2,
+    pub rotation: f32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+/// A frame looked up once by name, so drawing it doesn't hash the name again every time.
+/// Handles only make sense for the atlas that gave them out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameHandle(usize);
+
+/// Frames stored in a list that handles index into, with a map from names to handles
+#[derive(Debug, Default)]
+struct FrameTable {
+    frames: Vec<AtlasFrame>,
+    handles: HashMap<String, FrameHandle>,
+}
+
+impl FrameTable {
+    /// Returns false, leaving the table as it was, when the name is already taken
+    fn insert(&mut self, name: &str, frame: AtlasFrame) -> bool {
+        if self.handles.contains_key(name) {
+            return false;
+        }
+
+        self.handles.insert(name.to_owned(), FrameHandle(self.frames.len()));
+        self.frames.push(frame);
+        true
+    }
+
+    fn get_handle(&self, name: &str) -> Option<FrameHandle> {
+        self.handles.get(name).copied()
+    }
+
+    fn get(&self, name: &str) -> Option<&AtlasFrame> {
+        self.get_handle(name).map(|handle| &self.frames[handle.0])
+    }
+}
+
+impl FromIterator<(String, AtlasFrame)> for FrameTable {
+    fn from_iter<T: IntoIterator<Item = (String, AtlasFrame)>>(iter: T) -> Self {
+        let mut table = FrameTable::default();
+        for (name, frame) in iter {
+            table.insert(&name, frame);
+        }
+        table
+    }
+}
+
 pub struct TextureAtlas {
-    data: AtlasData,
     texture: Texture2D,
-    frames: HashMap<String, FrameRect>
+    frames: FrameTable,
+    clips: HashMap<String, AnimationClip>,
+    slices: HashMap<String, AtlasSlice>,
+    nine_slices: HashMap<String, NineSlice>,
+
+    // Data files for the other pages when this atlas is one page of a multipack
+    related_pages: Vec<String>,
+}
+
+/// What the position passed to the draw functions refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AtlasAnchor {
+    /// The top left corner of the untrimmed sprite
+    #[default]
+    TopLeft,
+
+    /// The frame's pivot, as exported by the packer
+    Pivot,
 }
 
 #[derive(Debug, Clone)]
@@ -81,11 +323,14 @@ pub struct AtlasTextureParams {
     pub flip_y: bool,
 
     /// Rotate around this point.
-    /// When `None`, rotate around the texture's center.
+    /// When `None`, rotate around the frame's pivot, which is the sprite's center unless the packer says otherwise.
     /// When `Some`, the coordinates are in screen-space.
     /// E.g. pivot (0,0) rotates around the top left corner of the screen, not of the
     /// texture.
     pub pivot: Option<Vec2>,
+
+    /// What the draw position refers to
+    pub anchor: AtlasAnchor,
 }
 
 impl Default for AtlasTextureParams {
@@ -95,76 +340,218 @@ impl Default for AtlasTextureParams {
             pivot: None,
             flip_x: false,
             flip_y: false,
+            anchor: AtlasAnchor::TopLeft,
         }
     }
 }
 
 impl TextureAtlas {
-    pub async fn from_data(data_path: &str, texture_path: Option<&str>) -> Result<Self, String> {
-        if let Ok(contents) = load_string(data_path).await {
-            if let Ok(mut atlas) = AtlasData::deserialize_json(&contents) {
-                let path = Path::new(&data_path);
-                let parent_path = path.parent().unwrap();
-                let t_path_buf = parent_path.join(&atlas.meta.image.as_ref().unwrap());
-
-                if let Ok(new_path) = t_path_buf.into_os_string().into_string(){
-                    atlas.meta.image = Some(new_path);
-                }
+    pub async fn from_data(data_path: &str, texture_path: Option<&str>) -> Result<Self, AtlasError> {
+        let contents = load_string(data_path).await.map_err(|_| AtlasError::MissingFile(data_path.to_owned()))?;
+        let atlas = AtlasData::deserialize_json(&contents).map_err(|err| AtlasError::parse(data_path, err))?;
 
-                let texture_path = match texture_path {
-                    Some(path) => path,
-                    None => {
-                        &atlas.meta.image.as_ref().unwrap()
-                    },
-                };
-                if let Ok(texture) = load_texture(texture_path).await {
-                    let mut frames = HashMap::new();
-                    for frame in atlas.frames.iter() {
-                        frames.insert(frame.filename.as_ref().unwrap().to_owned(), frame.frame.clone());
-                    }
-
-                    // Set the filter mode to be nearest for pixel perfection!
-                    texture.set_filter(macroquad::texture::FilterMode::Nearest);
-
-                    Ok(TextureAtlas {
-                        data: atlas,
-                        texture,
-                        frames
-                    })
-                }
-                else {
-                    Err(format!("Unable to load texture image at path {}", texture_path))
-                }
-            }
-            else {
-                Err(format!("Unable to deserialize AtlasData at path {}", data_path))
+        // The image named in the data is relative to the data file
+        let image_path = match texture_path {
+            Some(path) => path.to_owned(),
+            None => {
+                let image = atlas.meta.image.as_deref().ok_or_else(|| AtlasError::MissingImage { path: data_path.to_owned(), image: None })?;
+                Path::new(data_path).with_file_name(image).to_string_lossy().into_owned()
+            },
+        };
+        let texture = load_texture(&image_path).await.map_err(|_| AtlasError::MissingImage { path: data_path.to_owned(), image: Some(image_path.clone()) })?;
+
+        // Set the filter mode to be nearest for pixel perfection!
+        texture.set_filter(macroquad::texture::FilterMode::Nearest);
+
+        Self::from_atlas_data(data_path, atlas, texture)
+    }
+
+    /// Build an atlas from data already in memory, drawing from a texture the caller has loaded
+    pub fn from_json(json: &str, texture: Texture2D) -> Result<Self, AtlasError> {
+        let atlas = AtlasData::deserialize_json(json).map_err(|err| AtlasError::parse("", err))?;
+        Self::from_atlas_data("", atlas, texture)
+    }
+
+    fn from_atlas_data(data_path: &str, atlas: AtlasData, texture: Texture2D) -> Result<Self, AtlasError> {
+        let mut frames = FrameTable::default();
+        let mut frame_order = Vec::new();
+        for (index, frame) in atlas.frames.0.iter().enumerate() {
+            let name = frame.filename.as_deref().ok_or_else(|| AtlasError::invalid(data_path, format!("Frame {} has no filename", index)))?;
+            let atlas_frame = AtlasFrame::from_data(frame);
+            frame_order.push((name, atlas_frame.duration));
+
+            if !frames.insert(name, atlas_frame) {
+                return Err(AtlasError::DuplicateFrame { path: data_path.to_owned(), name: name.to_owned() });
             }
         }
-        else {
-            Err(format!("Unable to load AtlasData at path {}", data_path))
+
+        // Only Aseprite exports carry tags and slices, TexturePacker atlases get their clips from a sidecar file
+        let clips = match atlas.meta.frame_tags.as_ref() {
+            Some(tags) => aseprite::clips_from_tags(tags, &frame_order).map_err(|err| AtlasError::invalid(data_path, err))?,
+            None => HashMap::new(),
+        };
+        let slices: HashMap<_, _> = atlas.meta.slices.as_deref().map_or(Vec::new(), aseprite::slices_from_data)
+            .into_iter()
+            .map(|slice| (slice.name.clone(), slice))
+            .collect();
+
+        let related_pages = atlas.meta.related_multi_packs.iter().flatten()
+            .map(|page| Path::new(data_path).with_file_name(page).to_string_lossy().into_owned())
+            .collect();
+
+        let mut texture_atlas = TextureAtlas {
+            texture,
+            frames,
+            clips,
+            slices,
+            nine_slices: HashMap::new(),
+            related_pages,
+        };
+
+        // Slices with a 9-slice center drawn in Aseprite become nine-slices of the frame they were first keyed on
+        let nine_slices: Vec<_> = texture_atlas.slices.values().filter_map(|slice| {
+            let key = slice.keys.iter().find(|key| key.center.is_some())?;
+            let center = key.center?;
+            let (frame, _) = frame_order.get(key.frame)?;
+
+            Some((slice.name.clone(), NineSlice {
+                bounds: Some(key.bounds),
+                ..NineSlice::new(frame, NineSliceInsets {
+                    left: center.x,
+                    top: center.y,
+                    right: key.bounds.w - center.right(),
+                    bottom: key.bounds.h - center.bottom(),
+                })
+            }))
+        }).collect();
+
+        for (name, nine_slice) in nine_slices {
+            texture_atlas.add_nine_slice(&name, nine_slice).map_err(|err| err.with_path(data_path))?;
         }
+
+        Ok(texture_atlas)
     }
 
-    pub fn draw_texture(&self, texture: &str, x: f32, y: f32, rotation: f32, color: Color) {
-        if let Some(frame_data) = self.frames.get(texture) {
-            draw_texture_ex(self.texture, x, y, color, DrawTextureParams {
-                source: Some(Rect {x: frame_data.x, y: frame_data.y, w: frame_data.w, h: frame_data.h } ),
-                rotation,
-                ..Default::default()
-            })
+    /// Load animation clips from a sidecar JSON file, see `animation::clips_from_json` for the layout
+    pub async fn load_animations(&mut self, path: &str) -> Result<(), AtlasError> {
+        let contents = load_string(path).await.map_err(|_| AtlasError::MissingFile(path.to_owned()))?;
+        let clips = animation::clips_from_json(&contents).map_err(|err| err.with_path(path))?;
+
+        for (name, clip) in clips {
+            self.add_clip(&name, clip)?;
         }
+        Ok(())
     }
 
-    pub fn draw_texture_params(&self, texture: &str, x: f32, y: f32, color: Color, params: AtlasTextureParams) {
-        if let Some(frame_data) = self.frames.get(texture) {
-            draw_texture_ex(self.texture, x, y, color, DrawTextureParams {
-                source: Some(Rect {x: frame_data.x, y: frame_data.y, w: frame_data.w, h: frame_data.h } ),
-                rotation: params.rotation,
-                flip_x: params.flip_x,
-                flip_y: params.flip_y,
-                pivot: params.pivot,
-                ..Default::default()
-            })
+    /// Add a clip, checking every frame it refers to is in the atlas
+    pub fn add_clip(&mut self, name: &str, clip: AnimationClip) -> Result<(), AtlasError> {
+        if let Some(frame) = clip.get_frames().iter().find(|frame| !self.has_frame(&frame.name)) {
+            return Err(AtlasError::UnknownFrame(frame.name.clone()));
         }
+
+        self.clips.insert(name.to_owned(), clip);
+        Ok(())
+    }
+
+    pub fn get_clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    pub fn get_slice(&self, name: &str) -> Option<&AtlasSlice> {
+        self.slices.get(name)
+    }
+
+    /// Paths to the data files of the other pages of a multipack, empty for single page atlases
+    pub fn get_related_pages(&self) -> &[String] {
+        &self.related_pages
+    }
+
+    pub fn has_frame(&self, name: &str) -> bool {
+        self.frames.get_handle(name).is_some()
+    }
+
+    fn get_frame(&self, name: &str) -> Result<&AtlasFrame, AtlasError> {
+        self.frames.get(name).ok_or_else(|| AtlasError::UnknownFrame(name.to_owned()))
+    }
+
+    fn get_frame_by_handle(&self, handle: FrameHandle) -> &AtlasFrame {
+        // A handle from another atlas may happen to be in range here too, so this only catches some mixups
+        debug_assert!(handle.0 < self.frames.frames.len(), "FrameHandle {} doesn't belong to this atlas", handle.0);
+        &self.frames.frames[handle.0]
+    }
+
+    /// Size of a frame's sprite before it was trimmed for packing
+    pub fn get_frame_size(&self, name: &str) -> Result<Vec2, AtlasError> {
+        self.get_frame(name).map(|frame| frame.source_size)
+    }
+
+    pub fn get_handle_size(&self, handle: FrameHandle) -> Vec2 {
+        self.get_frame_by_handle(handle).source_size
+    }
+
+    /// Check a set of frame names up front, so draws using them can't fail later
+    pub fn check_frames<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Result<(), AtlasError> {
+        names.into_iter().try_for_each(|name| self.get_frame(name).map(|_| ()))
+    }
+
+    /// Resolve a frame name once, usually at load time, to draw it by handle from then on
+    pub fn get_handle(&self, name: &str) -> Result<FrameHandle, AtlasError> {
+        self.frames.get_handle(name).ok_or_else(|| AtlasError::UnknownFrame(name.to_owned()))
+    }
+
+    /// Resolve a list of frame names in order, failing on the first one the atlas doesn't have
+    pub fn get_handles<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Result<Vec<FrameHandle>, AtlasError> {
+        names.into_iter().map(|name| self.get_handle(name)).collect()
+    }
+
+    pub fn draw_texture(&self, texture: &str, x: f32, y: f32, rotation: f32, color: Color) -> Result<(), AtlasError> {
+        self.draw_texture_params(texture, x, y, color, AtlasTextureParams {
+            rotation,
+            ..Default::default()
+        })
+    }
+
+    pub fn draw_texture_params(&self, texture: &str, x: f32, y: f32, color: Color, params: AtlasTextureParams) -> Result<(), AtlasError> {
+        let frame = self.get_frame(texture)?;
+        self.draw_frame(frame, vec2(x, y), color, &params);
+        Ok(())
+    }
+
+    pub fn draw_handle(&self, handle: FrameHandle, x: f32, y: f32, rotation: f32, color: Color) {
+        self.draw_handle_params(handle, x, y, color, AtlasTextureParams {
+            rotation,
+            ..Default::default()
+        });
+    }
+
+    /// Same as `draw_texture_params` without the name lookup, and so without anything to go wrong
+    pub fn draw_handle_params(&self, handle: FrameHandle, x: f32, y: f32, color: Color, params: AtlasTextureParams) {
+        self.draw_frame(self.get_frame_by_handle(handle), vec2(x, y), color, &params);
+    }
+
+    pub fn get_texture(&self) -> Texture2D {
+        self.texture
     }
-}
\ No newline at end of file
+
+    /// Work out where a frame lands on screen, for drawing it some other way than through the atlas
+    pub fn get_quad(&self, texture: &str, x: f32, y: f32, params: &AtlasTextureParams) -> Result<FrameQuad, AtlasError> {
+        self.get_frame(texture).map(|frame| frame.get_quad(vec2(x, y), params))
+    }
+
+    pub fn get_handle_quad(&self, handle: FrameHandle, x: f32, y: f32, params: &AtlasTextureParams) -> FrameQuad {
+        self.get_frame_by_handle(handle).get_quad(vec2(x, y), params)
+    }
+
+    fn draw_frame(&self, frame: &AtlasFrame, position: Vec2, color: Color, params: &AtlasTextureParams) {
+        let quad = frame.get_quad(position, params);
+
+        draw_texture_ex(self.texture, quad.center.x - quad.size.x * 0.5, quad.center.y - quad.size.y * 0.5, color, DrawTextureParams {
+            dest_size: Some(quad.size),
+            source: Some(quad.region),
+            rotation: quad.rotation,
+            flip_x: quad.flip_x,
+            flip_y: quad.flip_y,
+            pivot: None,
+        });
+    }
+}
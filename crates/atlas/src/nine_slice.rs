@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use macroquad::{
+    prelude::{load_string, Color, Rect},
+    texture::{draw_texture_ex, DrawTextureParams},
+};
+use nanoserde::DeJson;
+
+use crate::{AtlasError, TextureAtlas};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NineSliceMode {
+    /// Scale the piece to fill the space
+    #[default]
+    Stretch,
+
+    /// Repeat the piece at its original size, cutting the last copy short
+    Tile,
+}
+
+impl NineSliceMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stretch" => Some(NineSliceMode::Stretch),
+            "tile" => Some(NineSliceMode::Tile),
+            _ => None,
+        }
+    }
+}
+
+/// Widths of the borders that keep their size when a nine-slice is resized, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NineSliceInsets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NineSlice {
+    /// Atlas frame the pieces are cut from
+    pub frame: String,
+
+    /// Part of the untrimmed frame to use, the whole frame when `None`
+    pub bounds: Option<Rect>,
+    pub insets: NineSliceInsets,
+    pub edge_mode: NineSliceMode,
+    pub center_mode: NineSliceMode,
+}
+
+impl NineSlice {
+    pub fn new(frame: &str, insets: NineSliceInsets) -> Self {
+        NineSlice {
+            frame: frame.to_owned(),
+            bounds: None,
+            insets,
+            edge_mode: NineSliceMode::Stretch,
+            center_mode: NineSliceMode::Stretch,
+        }
+    }
+}
+
+#[derive(DeJson)]
+struct NineSliceData {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    edges: Option<String>,
+    center: Option<String>,
+}
+
+#[derive(DeJson)]
+struct NineSliceFile {
+    nine_slices: HashMap<String, NineSliceData>,
+}
+
+/// Parse nine-slices from a sidecar JSON file, keyed by the frame they're cut from:
+///
+/// ```json
+/// { "nine_slices": { "panel.png": { "left": 4, "top": 4, "right": 4, "bottom": 4, "edges": "tile", "center": "stretch" } } }
+/// ```
+pub fn nine_slices_from_json(json: &str) -> Result<Vec<NineSlice>, AtlasError> {
+    let file = NineSliceFile::deserialize_json(json).map_err(|err| AtlasError::parse("", err))?;
+
+    let parse_mode = |frame: &str, mode: Option<&str>| match mode {
+        Some(mode) => NineSliceMode::from_name(mode).ok_or_else(|| AtlasError::invalid("", format!("Nine-slice {} has an unknown mode {}", frame, mode))),
+        None => Ok(NineSliceMode::Stretch),
+    };
+
+    file.nine_slices.iter().map(|(frame, data)| {
+        Ok(NineSlice {
+            edge_mode: parse_mode(frame, data.edges.as_deref())?,
+            center_mode: parse_mode(frame, data.center.as_deref())?,
+            ..NineSlice::new(frame, NineSliceInsets { left: data.left, top: data.top, right: data.right, bottom: data.bottom })
+        })
+    }).collect()
+}
+
+/// Split a source span over a destination span, repeating it when tiling.
+/// Returns (source start, source length, destination start, destination length) for each piece.
+fn spans(source: (f32, f32), dest: (f32, f32), mode: NineSliceMode) -> Vec<(f32, f32, f32, f32)> {
+    let (source_start, source_length) = source;
+    let (dest_start, dest_length) = dest;
+
+    if dest_length <= 0.0 || source_length <= 0.0 {
+        return Vec::new();
+    }
+
+    match mode {
+        NineSliceMode::Stretch => vec![(source_start, source_length, dest_start, dest_length)],
+        NineSliceMode::Tile => {
+            let mut pieces = Vec::new();
+            let mut offset = 0.0;
+            while offset < dest_length {
+                let length = source_length.min(dest_length - offset);
+                pieces.push((source_start, length, dest_start + offset, length));
+                offset += length;
+            }
+            pieces
+        },
+    }
+}
+
+impl TextureAtlas {
+    /// Load nine-slices from a sidecar JSON file, see `nine_slice::nine_slices_from_json` for the layout
+    pub async fn load_nine_slices(&mut self, path: &str) -> Result<(), AtlasError> {
+        let contents = load_string(path).await.map_err(|_| AtlasError::MissingFile(path.to_owned()))?;
+        let nine_slices = nine_slices_from_json(&contents).map_err(|err| err.with_path(path))?;
+
+        for nine_slice in nine_slices {
+            let name = nine_slice.frame.clone();
+            self.add_nine_slice(&name, nine_slice).map_err(|err| err.with_path(path))?;
+        }
+        Ok(())
+    }
+
+    /// Register a nine-slice under a name, usually the name of its frame
+    pub fn add_nine_slice(&mut self, name: &str, nine_slice: NineSlice) -> Result<(), AtlasError> {
+        let frame = self.frames.get(&nine_slice.frame).ok_or_else(|| AtlasError::UnknownFrame(nine_slice.frame.clone()))?;
+
+        // The pieces are cut straight out of the texture, so the frame has to be stored as drawn
+        if frame.rotated {
+            return Err(AtlasError::invalid("", format!("Nine-slice {} uses frame {} which is rotated in the atlas", name, nine_slice.frame)));
+        }
+
+        let bounds = nine_slice.bounds.unwrap_or(Rect::new(0., 0., frame.source_size.x, frame.source_size.y));
+        if bounds.x < frame.trim.x || bounds.y < frame.trim.y || bounds.right() > frame.trim.right() || bounds.bottom() > frame.trim.bottom() {
+            return Err(AtlasError::invalid("", format!("Nine-slice {} reaches into pixels trimmed from frame {}", name, nine_slice.frame)));
+        }
+
+        let insets = nine_slice.insets;
+        if insets.left + insets.right > bounds.w || insets.top + insets.bottom > bounds.h {
+            return Err(AtlasError::invalid("", format!("Nine-slice {} has insets larger than its frame", name)));
+        }
+
+        self.nine_slices.insert(name.to_owned(), nine_slice);
+        Ok(())
+    }
+
+    pub fn get_nine_slice(&self, name: &str) -> Option<&NineSlice> {
+        self.nine_slices.get(name)
+    }
+
+    /// Cut a nine-slice into (texture region, destination) pairs covering `dest`
+    pub fn get_nine_slice_pieces(&self, name: &str, dest: Rect) -> Result<Vec<(Rect, Rect)>, AtlasError> {
+        let nine_slice = self.nine_slices.get(name).ok_or_else(|| AtlasError::UnknownFrame(name.to_owned()))?;
+        let frame = self.get_frame(&nine_slice.frame)?;
+
+        let bounds = nine_slice.bounds.unwrap_or(Rect::new(0., 0., frame.source_size.x, frame.source_size.y));
+        let source = Rect::new(frame.region.x + bounds.x - frame.trim.x, frame.region.y + bounds.y - frame.trim.y, bounds.w, bounds.h);
+        let insets = nine_slice.insets;
+
+        // Shrink the borders evenly when the destination is too small to fit them
+        let scale_x = (dest.w / (insets.left + insets.right)).min(1.0);
+        let scale_y = (dest.h / (insets.top + insets.bottom)).min(1.0);
+        let (left, right) = (insets.left * scale_x, insets.right * scale_x);
+        let (top, bottom) = (insets.top * scale_y, insets.bottom * scale_y);
+
+        let columns = [
+            ((source.x, insets.left), (dest.x, left), false),
+            ((source.x + insets.left, source.w - insets.left - insets.right), (dest.x + left, dest.w - left - right), true),
+            ((source.right() - insets.right, insets.right), (dest.right() - right, right), false),
+        ];
+        let rows = [
+            ((source.y, insets.top), (dest.y, top), false),
+            ((source.y + insets.top, source.h - insets.top - insets.bottom), (dest.y + top, dest.h - top - bottom), true),
+            ((source.bottom() - insets.bottom, insets.bottom), (dest.bottom() - bottom, bottom), false),
+        ];
+
+        let mut pieces = Vec::new();
+        for (row_source, row_dest, row_middle) in rows.iter() {
+            for (column_source, column_dest, column_middle) in columns.iter() {
+                // Corners always stretch, edges follow the edge mode along their length, the center follows its own
+                let (mode_x, mode_y) = match (column_middle, row_middle) {
+                    (true, true) => (nine_slice.center_mode, nine_slice.center_mode),
+                    (true, false) => (nine_slice.edge_mode, NineSliceMode::Stretch),
+                    (false, true) => (NineSliceMode::Stretch, nine_slice.edge_mode),
+                    (false, false) => (NineSliceMode::Stretch, NineSliceMode::Stretch),
+                };
+
+                for (source_y, source_h, dest_y, dest_h) in spans(*row_source, *row_dest, mode_y) {
+                    for (source_x, source_w, dest_x, dest_w) in spans(*column_source, *column_dest, mode_x) {
+                        pieces.push((Rect::new(source_x, source_y, source_w, source_h), Rect::new(dest_x, dest_y, dest_w, dest_h)));
+                    }
+                }
+            }
+        }
+        Ok(pieces)
+    }
+
+    /// Draw a nine-slice resized to fill `dest`
+    pub fn draw_nine_slice(&self, name: &str, dest: Rect, color: Color) -> Result<(), AtlasError> {
+        for (region, piece) in self.get_nine_slice_pieces(name, dest)? {
+            draw_texture_ex(self.texture, piece.x, piece.y, color, DrawTextureParams {
+                dest_size: Some(piece.size()),
+                source: Some(region),
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+}
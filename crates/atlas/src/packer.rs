@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+
+use image::{imageops, RgbaImage};
+use macroquad::{prelude::Rect, texture::{FilterMode, Texture2D}};
+use nanoserde::SerJson;
+
+use crate::{AtlasData, AtlasError, AtlasFrame, AtlasSize, FrameData, FrameList, FramePivot, FrameRect, MetaData, TextureAtlas, animation};
+
+/// Tracks the top edge of everything packed so far as a list of horizontal segments
+struct Skyline {
+    width: u32,
+    height: u32,
+
+    // (x, y, width) of each segment, left to right
+    nodes: Vec<(u32, u32, u32)>,
+}
+
+impl Skyline {
+    fn new(width: u32, height: u32) -> Self {
+        Skyline { width, height, nodes: vec![(0, 0, width)] }
+    }
+
+    /// Lowest y a rect starting at node `index` can sit at without overlapping anything
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.nodes[index].0;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width as i64;
+        for &(_, node_y, node_width) in self.nodes[index..].iter() {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(node_y);
+            remaining -= node_width as i64;
+        }
+
+        (y + height <= self.height).then_some(y)
+    }
+
+    /// Place a rect as low as possible, preferring the left on ties
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (index, y) = (0..self.nodes.len())
+            .filter_map(|index| self.fit(index, width, height).map(|y| (index, y)))
+            .min_by_key(|&(index, y)| (y + height, self.nodes[index].0))?;
+
+        let x = self.nodes[index].0;
+        self.nodes.insert(index, (x, y + height, width));
+
+        // Trim the segments now covered by the new one
+        let right = x + width;
+        let next = index + 1;
+        while next < self.nodes.len() {
+            let (node_x, node_y, node_width) = self.nodes[next];
+            if node_x >= right {
+                break;
+            }
+
+            let node_right = node_x + node_width;
+            if node_right <= right {
+                self.nodes.remove(next);
+            }
+            else {
+                self.nodes[next] = (right, node_y, node_right - right);
+                break;
+            }
+        }
+
+        // Merge neighbours at the same height
+        let mut merge = 0;
+        while merge + 1 < self.nodes.len() {
+            if self.nodes[merge].1 == self.nodes[merge + 1].1 {
+                self.nodes[merge].2 += self.nodes[merge + 1].2;
+                self.nodes.remove(merge + 1);
+            }
+            else {
+                merge += 1;
+            }
+        }
+
+        Some((x, y))
+    }
+}
+
+/// Copy the outermost pixels of a placed image outwards so filtering at its edges doesn't pick up neighbours
+fn extrude(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, amount: u32) {
+    for offset in 1..=amount {
+        for column in x.saturating_sub(amount)..x + width + amount {
+            let source_x = column.clamp(x, x + width - 1);
+            let top = *image.get_pixel(source_x, y);
+            let bottom = *image.get_pixel(source_x, y + height - 1);
+            image.put_pixel(column, y - offset, top);
+            image.put_pixel(column, y + height - 1 + offset, bottom);
+        }
+        for row in y..y + height {
+            let left = *image.get_pixel(x, row);
+            let right = *image.get_pixel(x + width - 1, row);
+            image.put_pixel(x - offset, row, left);
+            image.put_pixel(x + width - 1 + offset, row, right);
+        }
+    }
+}
+
+/// Packs loose images into a single texture, replacing a TexturePacker project
+pub struct TexturePacker {
+    images: Vec<(String, RgbaImage)>,
+    padding: u32,
+    extrude: u32,
+    max_size: u32,
+}
+
+impl Default for TexturePacker {
+    fn default() -> Self {
+        TexturePacker {
+            images: Vec::new(),
+            padding: 2,
+            extrude: 1,
+            max_size: 4096,
+        }
+    }
+}
+
+impl TexturePacker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Empty pixels left between packed images
+    pub fn set_padding(&mut self, padding: u32) {
+        self.padding = padding;
+    }
+
+    /// How many times each image's edge pixels are repeated around it
+    pub fn set_extrude(&mut self, extrude: u32) {
+        self.extrude = extrude;
+    }
+
+    /// Largest width or height the packed texture may grow to, textures can't be larger than `u16::MAX` either way
+    pub fn set_max_size(&mut self, max_size: u32) {
+        self.max_size = max_size.min(u16::MAX as u32);
+    }
+
+    pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+        self.images.push((name.to_owned(), image));
+    }
+
+    pub fn add_png(&mut self, name: &str, bytes: &[u8]) -> Result<(), String> {
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+            .map_err(|err| format!("Unable to decode {}: {}", name, err))?;
+        self.add_image(name, image.to_rgba8());
+        Ok(())
+    }
+
+    /// Add every PNG in a directory, named by file name like TexturePacker does
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_directory(&mut self, path: &str) -> Result<(), String> {
+        let entries = std::fs::read_dir(path).map_err(|err| format!("Unable to read directory {}: {}", path, err))?;
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")))
+            .collect();
+
+        // Keep the output stable between runs
+        paths.sort();
+
+        for path in paths {
+            let name = path.file_name().and_then(|name| name.to_str()).ok_or_else(|| format!("Invalid file name {}", path.display()))?;
+            let bytes = std::fs::read(&path).map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+            self.add_png(name, &bytes)?;
+        }
+        Ok(())
+    }
+
+    fn try_pack(&self, order: &[usize], width: u32, height: u32) -> Option<Vec<(u32, u32)>> {
+        let border = self.extrude * 2 + self.padding;
+        let mut skyline = Skyline::new(width, height);
+        let mut positions = vec![(0, 0); self.images.len()];
+
+        for &index in order {
+            let image = &self.images[index].1;
+            let (x, y) = skyline.insert(image.width() + border, image.height() + border)?;
+            positions[index] = (x + self.extrude, y + self.extrude);
+        }
+        Some(positions)
+    }
+
+    /// Pack everything added so far. `image_name` is written to the JSON as the texture's path, relative to the JSON file.
+    pub fn pack(&self, image_name: &str) -> Result<PackedAtlas, String> {
+        if self.images.is_empty() {
+            return Err("Nothing to pack".to_owned());
+        }
+        if let Some((name, _)) = self.images.iter().find(|(name, _)| self.images.iter().filter(|(other, _)| other == name).count() > 1) {
+            return Err(format!("Duplicate image name {}", name));
+        }
+
+        // Tallest first keeps the skyline flat
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|&index| {
+            let image = &self.images[index].1;
+            (std::cmp::Reverse(image.height()), std::cmp::Reverse(image.width()))
+        });
+
+        // Start from the smallest power of two square that could hold everything and grow until it fits
+        let border = self.extrude * 2 + self.padding;
+        let area: u64 = self.images.iter().map(|(_, image)| (image.width() + border) as u64 * (image.height() + border) as u64).sum();
+        let mut size = ((area as f64).sqrt().ceil() as u32).next_power_of_two();
+        let (mut width, mut height) = (size, size / 2);
+
+        let positions = loop {
+            if width > self.max_size || height > self.max_size {
+                return Err(format!("Images don't fit in a {0}x{0} texture", self.max_size));
+            }
+            if height > 0 {
+                if let Some(positions) = self.try_pack(&order, width, height) {
+                    break positions;
+                }
+            }
+
+            // Alternate between a wide rectangle and a square
+            if height < width {
+                height = width;
+            }
+            else {
+                size *= 2;
+                width = size;
+                height = size / 2;
+            }
+        };
+
+        let mut image = RgbaImage::new(width, height);
+        let mut frames = Vec::with_capacity(self.images.len());
+
+        for (index, (name, source)) in self.images.iter().enumerate() {
+            let (x, y) = positions[index];
+            let (w, h) = source.dimensions();
+            imageops::replace(&mut image, source, x as i64, y as i64);
+            if w > 0 && h > 0 {
+                extrude(&mut image, x, y, w, h, self.extrude);
+            }
+
+            frames.push(FrameData {
+                filename: Some(name.clone()),
+                frame: FrameRect { x: x as f32, y: y as f32, w: w as f32, h: h as f32 },
+                rotated: false,
+                trimmed: false,
+                sprite_source_size: FrameRect { x: 0., y: 0., w: w as f32, h: h as f32 },
+                source_size: AtlasSize { w: w as f32, h: h as f32 },
+                pivot: Some(FramePivot { x: 0.5, y: 0.5 }),
+                duration: None,
+            });
+        }
+
+        let data = AtlasData {
+            frames: FrameList(frames),
+            meta: MetaData {
+                app: Some("atlas".to_owned()),
+                version: Some("1.0".to_owned()),
+                image: Some(image_name.to_owned()),
+                format: Some("RGBA8888".to_owned()),
+                size: AtlasSize { w: width as f32, h: height as f32 },
+                scale: Some("1".to_owned()),
+                smart_update: None,
+                frame_tags: None,
+                slices: None,
+                related_multi_packs: None,
+            },
+        };
+
+        Ok(PackedAtlas { image, data })
+    }
+}
+
+/// The result of packing, ready to save next to the game's assets or to use straight away
+pub struct PackedAtlas {
+    image: RgbaImage,
+    data: AtlasData,
+}
+
+impl PackedAtlas {
+    pub fn get_image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    /// Where an image ended up in the packed texture, not counting its extruded edges
+    pub fn get_frame_region(&self, name: &str) -> Option<Rect> {
+        self.data.frames.0.iter().find(|frame| frame.filename.as_deref() == Some(name)).map(|frame| {
+            let FrameRect { x, y, w, h } = frame.frame;
+            Rect::new(x, y, w, h)
+        })
+    }
+
+    /// The frame data in the same layout `TextureAtlas::from_data` reads
+    pub fn to_json(&self) -> String {
+        self.data.serialize_json()
+    }
+
+    /// Write the JSON and the texture, naming the texture after the image name given to `pack`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, json_path: &str) -> Result<(), String> {
+        let image_name = self.data.meta.image.as_deref().unwrap_or_default();
+        let image_path = std::path::Path::new(json_path).with_file_name(image_name);
+
+        self.image.save(&image_path).map_err(|err| format!("Unable to write {}: {}", image_path.display(), err))?;
+        std::fs::write(json_path, self.to_json()).map_err(|err| format!("Unable to write {}: {}", json_path, err))
+    }
+}
+
+impl TextureAtlas {
+    pub fn from_packed(packed: &PackedAtlas) -> Self {
+        let texture = Texture2D::from_rgba8(packed.image.width() as u16, packed.image.height() as u16, packed.image.as_raw());
+        texture.set_filter(FilterMode::Nearest);
+
+        let frames = packed.data.frames.0.iter().map(|frame| {
+            let FrameRect { x, y, w, h } = frame.frame;
+            (frame.filename.clone().unwrap_or_default(), AtlasFrame::new_untrimmed(Rect::new(x, y, w, h), animation::DEFAULT_FRAME_DURATION))
+        }).collect();
+
+        TextureAtlas {
+            texture,
+            frames,
+            clips: HashMap::new(),
+            slices: HashMap::new(),
+            nine_slices: HashMap::new(),
+            related_pages: Vec::new(),
+        }
+    }
+
+    /// Pack a directory of loose PNGs at load time
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_directory(path: &str) -> Result<Self, AtlasError> {
+        let mut packer = TexturePacker::new();
+        packer.add_directory(path).map_err(|err| AtlasError::invalid(path, err))?;
+        Ok(Self::from_packed(&packer.pack("packed.png").map_err(|err| AtlasError::invalid(path, err))?))
+    }
+}
@@ -0,0 +1,174 @@
+use macroquad::{
+    material::{gl_use_default_material, gl_use_material, load_material, Material, MaterialParams},
+    miniquad::{BlendFactor, BlendState, BlendValue, Equation, PipelineParams, UniformType},
+    prelude::{load_image, vec2, Color, Image},
+    texture::{FilterMode, Texture2D},
+};
+
+use crate::AtlasError;
+
+// GLSL ES 1.0 loops need a constant bound, so swap palettes are capped at this many colors
+const MAX_COLUMNS: u16 = 64;
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 100
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+uniform sampler2D Palette;
+uniform vec2 PaletteSize;
+uniform float PaletteRow;
+uniform float PaletteMode;
+
+vec3 palette_color(float column, float row) {
+    return texture2D(Palette, vec2((column + 0.5) / PaletteSize.x, (row + 0.5) / PaletteSize.y)).rgb;
+}
+
+void main() {
+    vec4 source = texture2D(Texture, uv);
+    vec4 result = source;
+
+    if (source.a > 0.0) {
+        if (PaletteMode < 0.5) {
+            // Find the color in the first row and take the same column from the chosen row, 64 is MAX_COLUMNS
+            for (int index = 0; index < 64; index++) {
+                float column = float(index);
+                if (column >= PaletteSize.x) {
+                    break;
+                }
+                if (all(lessThan(abs(palette_color(column, 0.0) - source.rgb), vec3(0.5 / 255.0)))) {
+                    result.rgb = palette_color(column, PaletteRow);
+                    break;
+                }
+            }
+        }
+        else {
+            // Brightness picks a spot along the row, darkest on the left
+            float luma = dot(source.rgb, vec3(0.299, 0.587, 0.114));
+            result.rgb = palette_color(floor(luma * (PaletteSize.x - 1.0) + 0.5), PaletteRow);
+        }
+    }
+
+    gl_FragColor = color * result;
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaletteMode {
+    /// Replace colors found in the palette's first row with the same column of the chosen row.
+    /// Colors that aren't in the first row are drawn unchanged.
+    #[default]
+    Swap,
+
+    /// Pick a color along the chosen row by brightness, for recoloring art that wasn't drawn with the palette in mind
+    Ramp,
+}
+
+/// Recolors sprites as they're drawn. Each row of the palette texture is one set of colors,
+/// with the first row holding the colors the art was drawn with.
+pub struct Palette {
+    texture: Texture2D,
+    material: Material,
+}
+
+impl Palette {
+    pub fn new(texture: Texture2D) -> Result<Self, AtlasError> {
+        if texture.width() > MAX_COLUMNS as f32 {
+            return Err(AtlasError::invalid("", format!("Palettes can have at most {} colors per row", MAX_COLUMNS)));
+        }
+        texture.set_filter(FilterMode::Nearest);
+
+        let material = load_material(VERTEX_SHADER, FRAGMENT_SHADER, MaterialParams {
+            pipeline_params: PipelineParams {
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+            uniforms: vec![
+                ("PaletteSize".to_owned(), UniformType::Float2),
+                ("PaletteRow".to_owned(), UniformType::Float1),
+                ("PaletteMode".to_owned(), UniformType::Float1),
+            ],
+            textures: vec!["Palette".to_owned()],
+        }).map_err(|err| AtlasError::invalid("", format!("Unable to build the palette shader: {}", err)))?;
+
+        material.set_texture("Palette", texture);
+        material.set_uniform("PaletteSize", vec2(texture.width(), texture.height()));
+
+        Ok(Palette { texture, material })
+    }
+
+    /// Load a palette image, one palette per row of pixels
+    pub async fn from_file(path: &str) -> Result<Self, AtlasError> {
+        let image = load_image(path).await.map_err(|_| AtlasError::MissingImage { path: path.to_owned(), image: Some(path.to_owned()) })?;
+        Self::new(Texture2D::from_image(&image)).map_err(|err| err.with_path(path))
+    }
+
+    /// Build a palette from rows of colors, which must all be the same length
+    pub fn from_rows(rows: &[Vec<Color>]) -> Result<Self, AtlasError> {
+        let columns = rows.first().map_or(0, |row| row.len());
+        if columns == 0 || rows.iter().any(|row| row.len() != columns) {
+            return Err(AtlasError::invalid("", "Palette rows must be the same length and not empty"));
+        }
+
+        let mut image = Image::gen_image_color(columns as u16, rows.len() as u16, Color::new(0., 0., 0., 0.));
+        for (y, row) in rows.iter().enumerate() {
+            for (x, color) in row.iter().enumerate() {
+                image.set_pixel(x as u32, y as u32, *color);
+            }
+        }
+
+        Self::new(Texture2D::from_image(&image))
+    }
+
+    pub fn get_texture(&self) -> Texture2D {
+        self.texture
+    }
+
+    pub fn get_row_count(&self) -> usize {
+        self.texture.height() as usize
+    }
+
+    /// Recolor everything drawn until `end`, including `SpriteBatch` draws.
+    /// Calling it again with another row between draws switches row without ending first.
+    pub fn begin(&self, row: usize, mode: PaletteMode) {
+        self.material.set_uniform("PaletteRow", row.min(self.get_row_count().saturating_sub(1)) as f32);
+        self.material.set_uniform("PaletteMode", match mode {
+            PaletteMode::Swap => 0.0f32,
+            PaletteMode::Ramp => 1.0f32,
+        });
+        gl_use_material(self.material);
+    }
+
+    /// Go back to drawing with macroquad's default material
+    pub fn end(&self) {
+        gl_use_default_material();
+    }
+}
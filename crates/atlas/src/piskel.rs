@@ -0,0 +1,194 @@
+use image::{imageops, RgbaImage};
+use macroquad::prelude::load_string;
+use nanoserde::DeJson;
+
+use crate::{AtlasError, TextureAtlas, animation::{self, AnimationClip, AnimationMode}, packer::TexturePacker};
+
+#[derive(DeJson)]
+struct PiskelFile {
+    #[nserde(rename = "modelVersion")]
+    model_version: u32,
+    piskel: PiskelData,
+}
+
+#[derive(DeJson)]
+struct PiskelData {
+    name: String,
+    fps: f32,
+    width: u32,
+    height: u32,
+
+    // Each layer is itself a JSON document stored as a string
+    layers: Vec<String>,
+}
+
+#[derive(DeJson)]
+struct LayerData {
+    opacity: Option<f32>,
+    #[nserde(rename = "frameCount")]
+    frame_count: usize,
+
+    // Version 2 files split the frames over chunks, version 1 has a single strip on the layer
+    chunks: Option<Vec<ChunkData>>,
+    #[nserde(rename = "base64PNG")]
+    base64_png: Option<String>,
+}
+
+#[derive(DeJson)]
+struct ChunkData {
+    /// Frame indices for each column of the chunk image, top to bottom
+    layout: Vec<Vec<usize>>,
+    #[nserde(rename = "base64PNG")]
+    base64_png: String,
+}
+
+/// The flattened contents of a piskel file
+pub struct PiskelSprite {
+    pub name: String,
+    pub fps: f32,
+    pub frames: Vec<RgbaImage>,
+}
+
+/// Decode standard base64, skipping whitespace and padding
+pub fn decode_base64(data: &str) -> Result<Vec<u8>, String> {
+    fn value(byte: u8) -> Option<u32> {
+        match byte {
+            b'A'..=b'Z' => Some((byte - b'A') as u32),
+            b'a'..=b'z' => Some((byte - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((byte - b'0') as u32 + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0;
+    let mut bits = 0;
+
+    for byte in data.bytes().filter(|byte| !byte.is_ascii_whitespace() && *byte != b'=') {
+        let value = value(byte).ok_or_else(|| format!("Invalid base64 character {}", byte as char))?;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn decode_png(data_url: &str) -> Result<RgbaImage, String> {
+    // Chunks are stored as data URLs, strip the "data:image/png;base64," header
+    let data = data_url.split_once(',').map_or(data_url, |(_, data)| data);
+    let bytes = decode_base64(data)?;
+
+    image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)
+        .map(|image| image.to_rgba8())
+        .map_err(|err| format!("Unable to decode layer image: {}", err))
+}
+
+/// Multiply every pixel's alpha, for layers with their opacity turned down
+fn apply_opacity(image: &mut RgbaImage, opacity: f32) {
+    if opacity < 1.0 {
+        for pixel in image.pixels_mut() {
+            pixel.0[3] = (pixel.0[3] as f32 * opacity).round() as u8;
+        }
+    }
+}
+
+/// Read a `.piskel` file, blending its layers into one image per frame
+pub fn parse_piskel(json: &str) -> Result<PiskelSprite, AtlasError> {
+    let file = PiskelFile::deserialize_json(json).map_err(|err| AtlasError::parse("", err))?;
+    if file.model_version > 2 {
+        return Err(AtlasError::invalid("", format!("Unsupported piskel model version {}", file.model_version)));
+    }
+
+    let PiskelData { name, fps, width, height, layers } = file.piskel;
+    let mut frames: Vec<RgbaImage> = Vec::new();
+
+    // Layers are listed bottom to top, so each one is blended over the ones before it
+    for (layer_index, layer) in layers.iter().enumerate() {
+        let layer = LayerData::deserialize_json(layer).map_err(|err| AtlasError::invalid("", format!("Unable to deserialize layer {}: {}", layer_index, err)))?;
+
+        if frames.is_empty() {
+            frames = (0..layer.frame_count).map(|_| RgbaImage::new(width, height)).collect();
+        }
+        else if frames.len() != layer.frame_count {
+            return Err(AtlasError::invalid("", format!("Layer {} has {} frames, expected {}", layer_index, layer.frame_count, frames.len())));
+        }
+
+        let chunks = match (layer.chunks, layer.base64_png) {
+            (Some(chunks), _) => chunks,
+            (None, Some(base64_png)) => vec![ChunkData {
+                layout: (0..layer.frame_count).map(|frame| vec![frame]).collect(),
+                base64_png,
+            }],
+            (None, None) => return Err(AtlasError::invalid("", format!("Layer {} has no image data", layer_index))),
+        };
+
+        for chunk in chunks {
+            let mut image = decode_png(&chunk.base64_png).map_err(|err| AtlasError::invalid("", err))?;
+            apply_opacity(&mut image, layer.opacity.unwrap_or(1.0));
+
+            for (column, rows) in chunk.layout.iter().enumerate() {
+                for (row, frame) in rows.iter().enumerate() {
+                    let (x, y) = (column as u32 * width, row as u32 * height);
+                    if x + width > image.width() || y + height > image.height() {
+                        return Err(AtlasError::invalid("", format!("Frame {} lies outside its chunk in layer {}", frame, layer_index)));
+                    }
+
+                    let target = frames.get_mut(*frame).ok_or_else(|| AtlasError::invalid("", format!("Layer {} refers to unknown frame {}", layer_index, frame)))?;
+                    let source = imageops::crop_imm(&image, x, y, width, height).to_image();
+                    imageops::overlay(target, &source, 0, 0);
+                }
+            }
+        }
+    }
+
+    if frames.is_empty() {
+        return Err(AtlasError::invalid("", format!("Piskel {} has no frames", name)));
+    }
+
+    Ok(PiskelSprite { name, fps, frames })
+}
+
+impl TextureAtlas {
+    /// Load a `.piskel` file straight from Piskel, skipping the export and repack.
+    /// Frames are named `frame_names[index]`, or `<piskel name>_<index>` when no names are given,
+    /// and the whole sprite is added as a clip under the piskel's name.
+    pub async fn from_piskel(path: &str, frame_names: Option<&[&str]>) -> Result<Self, AtlasError> {
+        let contents = load_string(path).await.map_err(|_| AtlasError::MissingFile(path.to_owned()))?;
+        let sprite = parse_piskel(&contents).map_err(|err| err.with_path(path))?;
+
+        let names: Vec<String> = match frame_names {
+            Some(names) if names.len() == sprite.frames.len() => names.iter().map(|name| name.to_string()).collect(),
+            Some(names) => return Err(AtlasError::invalid(path, format!("Got {} frame names for {} frames", names.len(), sprite.frames.len()))),
+            None => (0..sprite.frames.len()).map(|index| format!("{}_{}", sprite.name, index)).collect(),
+        };
+
+        // Piskel saves 0 fps for sprites that were never meant to animate
+        let duration = if sprite.fps > 0.0 { 1.0 / sprite.fps } else { animation::DEFAULT_FRAME_DURATION };
+
+        // Pack the frames like any other loose images, a long strip would soon outgrow the largest texture allowed
+        let mut packer = TexturePacker::new();
+        let mut clip = AnimationClip::new(AnimationMode::Loop);
+        for (index, (frame, name)) in sprite.frames.into_iter().zip(names.iter()).enumerate() {
+            if names[..index].contains(name) {
+                return Err(AtlasError::DuplicateFrame { path: path.to_owned(), name: name.clone() });
+            }
+
+            packer.add_image(name, frame);
+            clip.push_frame(name, duration);
+        }
+
+        let packed = packer.pack("").map_err(|err| AtlasError::invalid(path, err))?;
+        let mut atlas = TextureAtlas::from_packed(&packed);
+        atlas.add_clip(&sprite.name, clip)?;
+
+        Ok(atlas)
+    }
+}
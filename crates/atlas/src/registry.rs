@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::{Color, Vec2};
+
+use crate::{AtlasError, AtlasTextureParams, FrameHandle, TextureAtlas};
+
+/// A frame in one of the registry's pages, resolved once from its namespaced name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AtlasHandle {
+    page: usize,
+    frame: FrameHandle,
+}
+
+impl AtlasHandle {
+    /// The frame within its page, see `AtlasRegistry::get_page`
+    pub fn get_frame(&self) -> FrameHandle {
+        self.frame
+    }
+}
+
+struct Namespace {
+    /// Data file of each page, empty for atlases built in code
+    paths: Vec<String>,
+
+    /// Indices into the registry's pages
+    pages: Vec<usize>,
+}
+
+impl Namespace {
+    fn describe(&self) -> &str {
+        self.paths.first().map_or("an atlas built in code", String::as_str)
+    }
+}
+
+/// Owns every atlas the game draws from, so systems share textures and refer to frames as `namespace/frame`
+#[derive(Default)]
+pub struct AtlasRegistry {
+    pages: Vec<TextureAtlas>,
+    namespaces: HashMap<String, Namespace>,
+
+    // Every frame of every namespace, keyed by its full name
+    frames: HashMap<String, AtlasHandle>,
+}
+
+impl AtlasRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load an atlas, along with the other pages when it's part of a TexturePacker multipack, returning the first page.
+    /// Asking for a file that's already loaded shares the pages that are there, so each system can load what it needs.
+    pub async fn load(&mut self, namespace: &str, data_path: &str) -> Result<&mut TextureAtlas, AtlasError> {
+        self.load_namespace(namespace, data_path).await?;
+
+        // Every namespace has at least the page its data file was loaded into
+        let page = self.namespaces[namespace].pages[0];
+        Ok(&mut self.pages[page])
+    }
+
+    async fn load_namespace(&mut self, namespace: &str, data_path: &str) -> Result<(), AtlasError> {
+        Self::check_namespace(namespace).map_err(|err| err.with_path(data_path))?;
+
+        // Any page of a multipack stands for the whole atlas
+        if let Some(existing) = self.namespaces.get(namespace) {
+            if existing.paths.iter().any(|path| path == data_path) {
+                return Ok(());
+            }
+            return Err(AtlasError::invalid(data_path, format!("Namespace {} is already used by {}", namespace, existing.describe())));
+        }
+
+        if let Some(existing) = self.namespaces.values().find(|existing| existing.paths.iter().any(|path| path == data_path)) {
+            let (paths, pages) = (existing.paths.clone(), existing.pages.clone());
+            return self.add_namespace(namespace, paths, pages);
+        }
+
+        // Every page of a multipack lists all the others, so loading any one of them finds the rest
+        let mut paths = vec![data_path.to_owned()];
+        let mut atlases = Vec::new();
+        while let Some(path) = paths.get(atlases.len()) {
+            let atlas = TextureAtlas::from_data(path, None).await?;
+            for related in atlas.get_related_pages() {
+                if !paths.contains(related) {
+                    paths.push(related.clone());
+                }
+            }
+            atlases.push(atlas);
+        }
+
+        self.add_pages(namespace, paths, atlases)
+    }
+
+    /// Add an atlas built in code, such as a packed directory or a piskel, under a namespace of its own
+    pub fn insert(&mut self, namespace: &str, atlas: TextureAtlas) -> Result<(), AtlasError> {
+        Self::check_namespace(namespace)?;
+
+        if let Some(existing) = self.namespaces.get(namespace) {
+            return Err(AtlasError::invalid("", format!("Namespace {} is already used by {}", namespace, existing.describe())));
+        }
+
+        self.add_pages(namespace, Vec::new(), vec![atlas])
+    }
+
+    fn check_namespace(namespace: &str) -> Result<(), AtlasError> {
+        if namespace.is_empty() || namespace.contains('/') {
+            return Err(AtlasError::invalid("", format!("Invalid atlas namespace {}", namespace)));
+        }
+        Ok(())
+    }
+
+    fn add_pages(&mut self, namespace: &str, paths: Vec<String>, atlases: Vec<TextureAtlas>) -> Result<(), AtlasError> {
+        let first = self.pages.len();
+        self.pages.extend(atlases);
+
+        let pages = (first..self.pages.len()).collect();
+        let result = self.add_namespace(namespace, paths, pages);
+        if result.is_err() {
+            self.pages.truncate(first);
+        }
+        result
+    }
+
+    fn add_namespace(&mut self, namespace: &str, paths: Vec<String>, pages: Vec<usize>) -> Result<(), AtlasError> {
+        let mut frames = HashMap::new();
+        for &page in pages.iter() {
+            for (name, &frame) in self.pages[page].frames.handles.iter() {
+                if frames.insert(format!("{}/{}", namespace, name), AtlasHandle { page, frame }).is_some() {
+                    let path = paths.first().map_or("", String::as_str);
+                    return Err(AtlasError::DuplicateFrame { path: path.to_owned(), name: name.clone() });
+                }
+            }
+        }
+
+        self.frames.extend(frames);
+        self.namespaces.insert(namespace.to_owned(), Namespace { paths, pages });
+        Ok(())
+    }
+
+    pub fn has_namespace(&self, namespace: &str) -> bool {
+        self.namespaces.contains_key(namespace)
+    }
+
+    /// The first page of a namespace, which is the whole atlas unless it's a multipack.
+    /// Clips and nine-slices live on the page they were loaded into, see `get_pages` and `find_clip_page` for the others.
+    pub fn get_atlas(&self, namespace: &str) -> Option<&TextureAtlas> {
+        self.get_pages(namespace).next()
+    }
+
+    pub fn get_atlas_mut(&mut self, namespace: &str) -> Option<&mut TextureAtlas> {
+        self.get_page_mut(namespace, 0)
+    }
+
+    /// Every page of a namespace, in the order they were loaded
+    pub fn get_pages<'a>(&'a self, namespace: &str) -> impl Iterator<Item = &'a TextureAtlas> + 'a {
+        self.namespaces.get(namespace).into_iter().flat_map(move |existing| existing.pages.iter().map(move |&page| &self.pages[page]))
+    }
+
+    pub fn get_page_mut(&mut self, namespace: &str, index: usize) -> Option<&mut TextureAtlas> {
+        let page = *self.namespaces.get(namespace)?.pages.get(index)?;
+        self.pages.get_mut(page)
+    }
+
+    /// The page of a namespace holding a clip, which is the atlas to play it from
+    pub fn find_clip_page(&self, namespace: &str, clip: &str) -> Option<&TextureAtlas> {
+        self.get_pages(namespace).find(|page| page.get_clip(clip).is_some())
+    }
+
+    /// The page a frame lives on
+    pub fn get_page(&self, handle: AtlasHandle) -> &TextureAtlas {
+        &self.pages[handle.page]
+    }
+
+    /// Resolve a `namespace/frame` name, where the frame is named exactly as in its atlas, slashes and all
+    pub fn get_handle(&self, name: &str) -> Result<AtlasHandle, AtlasError> {
+        self.frames.get(name).copied().ok_or_else(|| AtlasError::UnknownFrame(name.to_owned()))
+    }
+
+    pub fn get_handles<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Result<Vec<AtlasHandle>, AtlasError> {
+        names.into_iter().map(|name| self.get_handle(name)).collect()
+    }
+
+    /// Resolve frames by their names within one namespace, the way the atlas itself names them
+    pub fn get_namespace_handles<'a>(&self, namespace: &str, names: impl IntoIterator<Item = &'a str>) -> Result<Vec<AtlasHandle>, AtlasError> {
+        names.into_iter().map(|name| self.get_handle(&format!("{}/{}", namespace, name))).collect()
+    }
+
+    pub fn get_handle_size(&self, handle: AtlasHandle) -> Vec2 {
+        self.get_page(handle).get_handle_size(handle.frame)
+    }
+
+    pub fn draw_handle(&self, handle: AtlasHandle, x: f32, y: f32, rotation: f32, color: Color) {
+        self.get_page(handle).draw_handle(handle.frame, x, y, rotation, color);
+    }
+
+    pub fn draw_handle_params(&self, handle: AtlasHandle, x: f32, y: f32, color: Color, params: AtlasTextureParams) {
+        self.get_page(handle).draw_handle_params(handle.frame, x, y, color, params);
+    }
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use atlas::{
+    animation::{clips_from_json, AnimationClip, AnimationEvent, AnimationMode, AnimationPlayer},
+    TextureAtlas,
+};
+use common::{atlas_from_json, atlas_json, untrimmed_json};
+
+// A quarter second is exact in binary, so frame boundaries land exactly where expected
+const FRAME_DURATION: f32 = 0.25;
+
+fn test_atlas() -> TextureAtlas {
+    let frames: Vec<String> = (0..3).map(|index| untrimmed_json(&format!("frame_{}", index), index as f32 * 16.0, 0.0, 16.0, 16.0)).collect();
+    let mut atlas = atlas_from_json(&atlas_json(&frames, ""));
+
+    for (name, mode) in [("loop", AnimationMode::Loop), ("pingpong", AnimationMode::PingPong), ("once", AnimationMode::Once)] {
+        let mut clip = AnimationClip::new(mode);
+        for index in 0..3 {
+            clip.push_frame(&format!("frame_{}", index), FRAME_DURATION);
+        }
+        atlas.add_clip(name, clip).unwrap();
+    }
+
+    atlas
+}
+
+fn playing(atlas: &TextureAtlas, clip: &str) -> AnimationPlayer {
+    let mut player = AnimationPlayer::new();
+    assert!(player.play(atlas, clip));
+    player
+}
+
+/// Step through a frame at a time, recording the frame shown and any event after each step
+fn step(player: &mut AnimationPlayer, steps: usize) -> Vec<(usize, Option<AnimationEvent>)> {
+    (0..steps).map(|_| {
+        let event = player.update(FRAME_DURATION);
+        (player.get_frame_index(), event)
+    }).collect()
+}
+
+#[test]
+fn loop_wraps_to_the_first_frame() {
+    let atlas = test_atlas();
+    let mut player = playing(&atlas, "loop");
+
+    assert_eq!(player.update(FRAME_DURATION * 0.5), None);
+    assert_eq!(player.get_frame_index(), 0);
+    assert_eq!(player.get_frame_name(), Some("frame_0"));
+
+    player.restart();
+    assert_eq!(step(&mut player, 4), vec![
+        (1, None),
+        (2, None),
+        (0, Some(AnimationEvent::Looped("loop".to_owned()))),
+        (1, None),
+    ]);
+}
+
+#[test]
+fn ping_pong_runs_back_without_repeating_the_ends() {
+    let atlas = test_atlas();
+    let mut player = playing(&atlas, "pingpong");
+
+    assert_eq!(step(&mut player, 6), vec![
+        (1, None),
+        (2, None),
+        (1, None),
+        (0, Some(AnimationEvent::Looped("pingpong".to_owned()))),
+        (1, None),
+        (2, None),
+    ]);
+}
+
+#[test]
+fn once_stops_on_the_last_frame() {
+    let atlas = test_atlas();
+    let mut player = playing(&atlas, "once");
+
+    assert_eq!(step(&mut player, 3), vec![
+        (1, None),
+        (2, None),
+        (2, Some(AnimationEvent::Finished("once".to_owned()))),
+    ]);
+    assert!(player.is_finished());
+
+    // Nothing more happens until it's restarted
+    assert_eq!(player.update(10.0), None);
+    assert_eq!(player.get_frame_index(), 2);
+
+    player.restart();
+    assert!(!player.is_finished());
+    assert_eq!(player.get_frame_index(), 0);
+}
+
+#[test]
+fn long_updates_skip_frames_and_still_report_the_loop() {
+    let atlas = test_atlas();
+    let mut player = playing(&atlas, "loop");
+
+    assert_eq!(player.update(FRAME_DURATION * 4.5), Some(AnimationEvent::Looped("loop".to_owned())));
+    assert_eq!(player.get_frame_index(), 1);
+}
+
+#[test]
+fn speed_scales_the_time_passed_in() {
+    let atlas = test_atlas();
+    let mut player = playing(&atlas, "loop");
+
+    player.set_speed(2.0);
+    player.update(FRAME_DURATION);
+    assert_eq!(player.get_frame_index(), 2);
+}
+
+#[test]
+fn playing_the_current_clip_carries_on() {
+    let atlas = test_atlas();
+    let mut player = playing(&atlas, "loop");
+
+    player.update(FRAME_DURATION);
+    assert!(player.play(&atlas, "loop"));
+    assert_eq!(player.get_frame_index(), 1);
+
+    // Switching clips starts the new one from its first frame
+    assert!(player.play(&atlas, "once"));
+    assert_eq!(player.get_clip_name(), Some("once"));
+    assert_eq!(player.get_frame_index(), 0);
+}
+
+#[test]
+fn unknown_clips_leave_the_player_as_it_was() {
+    let atlas = test_atlas();
+    let mut player = playing(&atlas, "loop");
+
+    assert!(!player.play(&atlas, "missing"));
+    assert_eq!(player.get_clip_name(), Some("loop"));
+}
+
+#[test]
+fn sidecar_clips_read_durations_in_milliseconds() {
+    let clips = clips_from_json(r#"{"clips": {
+        "walk": {"frames": ["a", "b"], "durations": [100, 250], "mode": "pingpong", "flip_x": true},
+        "idle": {"frames": ["a"], "duration": 500}
+    }}"#).unwrap();
+
+    let walk = &clips["walk"];
+    assert_eq!(walk.get_mode(), AnimationMode::PingPong);
+    assert_eq!(walk.get_frames().iter().map(|frame| frame.duration).collect::<Vec<_>>(), vec![0.1, 0.25]);
+    assert!(walk.get_flip_x() && !walk.get_flip_y());
+
+    assert_eq!(clips["idle"].get_mode(), AnimationMode::Loop);
+    assert_eq!(clips["idle"].get_duration(), 0.5);
+}
+
+#[test]
+fn sidecar_clips_reject_mismatched_durations_and_unknown_modes() {
+    assert!(clips_from_json(r#"{"clips": {"walk": {"frames": ["a", "b"], "durations": [100]}}}"#).is_err());
+    assert!(clips_from_json(r#"{"clips": {"walk": {"frames": ["a"], "mode": "sideways"}}}"#).is_err());
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use atlas::{animation::AnimationMode, AtlasError, TextureAtlas};
+use common::{atlas_json, untrimmed_json};
+use macroquad::{prelude::{vec2, Rect}, texture::Texture2D};
+
+fn from_json(json: &str) -> Result<TextureAtlas, AtlasError> {
+    TextureAtlas::from_json(json, Texture2D::empty())
+}
+
+/// Four 16x16 frames in Aseprite's hash layout, each shown for `index + 1` tenths of a second
+fn hash_json(meta: &str) -> String {
+    let frames: Vec<String> = (0..4).map(|index| format!(
+        r#""walk {}.aseprite": {{"frame": {{"x": {}, "y": 0, "w": 16, "h": 16}}, "rotated": false, "trimmed": false,
+            "spriteSourceSize": {{"x": 0, "y": 0, "w": 16, "h": 16}}, "sourceSize": {{"w": 16, "h": 16}}, "duration": {}}}"#,
+        index, index * 16, (index + 1) * 100
+    )).collect();
+
+    format!(r#"{{"frames": {{{}}}, "meta": {{"size": {{"w": 64, "h": 16}}, {}}}}}"#, frames.join(", "), meta)
+}
+
+fn tag(name: &str, from: usize, to: usize, direction: &str) -> String {
+    format!(r#"{{"name": "{}", "from": {}, "to": {}, "direction": "{}"}}"#, name, from, to, direction)
+}
+
+fn tags_json(tags: &[String]) -> String {
+    hash_json(&format!(r#""frameTags": [{}]"#, tags.join(", ")))
+}
+
+fn clip_frames(atlas: &TextureAtlas, clip: &str) -> Vec<String> {
+    atlas.get_clip(clip).unwrap().get_frames().iter().map(|frame| frame.name.clone()).collect()
+}
+
+fn names(indices: &[usize]) -> Vec<String> {
+    indices.iter().map(|index| format!("walk {}.aseprite", index)).collect()
+}
+
+#[test]
+fn hash_and_array_layouts_read_the_same_frames() {
+    let hash = from_json(&hash_json(r#""app": "aseprite""#)).unwrap();
+    let array = from_json(&atlas_json(&(0..4).map(|index| untrimmed_json(&format!("walk {}.aseprite", index), index as f32 * 16.0, 0.0, 16.0, 16.0)).collect::<Vec<_>>(), "")).unwrap();
+
+    for name in names(&[0, 1, 2, 3]) {
+        assert_eq!(hash.get_frame_size(&name).unwrap(), vec2(16.0, 16.0));
+        assert_eq!(
+            hash.get_quad(&name, 0.0, 0.0, &Default::default()).unwrap().region,
+            array.get_quad(&name, 0.0, 0.0, &Default::default()).unwrap().region
+        );
+    }
+}
+
+#[test]
+fn frames_must_be_an_array_or_object() {
+    assert!(matches!(from_json(r#"{"frames": 4, "meta": {"size": {"w": 1, "h": 1}}}"#), Err(AtlasError::Parse { .. })));
+}
+
+#[test]
+fn duplicate_frame_names_are_rejected() {
+    let frame = untrimmed_json("twice.png", 0.0, 0.0, 8.0, 8.0);
+    assert!(matches!(from_json(&atlas_json(&[frame.clone(), frame], "")), Err(AtlasError::DuplicateFrame { .. })));
+}
+
+#[test]
+fn tags_become_clips_with_their_frame_durations() {
+    let atlas = from_json(&tags_json(&[tag("walk", 1, 3, "forward")])).unwrap();
+    let clip = atlas.get_clip("walk").unwrap();
+
+    assert_eq!(clip.get_mode(), AnimationMode::Loop);
+    assert_eq!(clip_frames(&atlas, "walk"), names(&[1, 2, 3]));
+    assert_eq!(clip.get_frames().iter().map(|frame| frame.duration).collect::<Vec<_>>(), vec![0.2, 0.3, 0.4]);
+}
+
+#[test]
+fn tag_directions_set_the_order_and_mode() {
+    let atlas = from_json(&tags_json(&[
+        tag("reverse", 0, 2, "reverse"),
+        tag("pingpong", 0, 2, "pingpong"),
+        tag("pingpong_reverse", 0, 2, "pingpong_reverse"),
+    ])).unwrap();
+
+    assert_eq!(clip_frames(&atlas, "reverse"), names(&[2, 1, 0]));
+    assert_eq!(atlas.get_clip("reverse").unwrap().get_mode(), AnimationMode::Loop);
+
+    assert_eq!(clip_frames(&atlas, "pingpong"), names(&[0, 1, 2]));
+    assert_eq!(atlas.get_clip("pingpong").unwrap().get_mode(), AnimationMode::PingPong);
+
+    assert_eq!(clip_frames(&atlas, "pingpong_reverse"), names(&[2, 1, 0]));
+    assert_eq!(atlas.get_clip("pingpong_reverse").unwrap().get_mode(), AnimationMode::PingPong);
+}
+
+#[test]
+fn tags_set_to_repeat_once_stop_at_the_end() {
+    let atlas = from_json(&tags_json(&[r#"{"name": "die", "from": 0, "to": 3, "direction": "forward", "repeat": "1"}"#.to_owned()])).unwrap();
+    assert_eq!(atlas.get_clip("die").unwrap().get_mode(), AnimationMode::Once);
+}
+
+#[test]
+fn bad_tags_are_rejected() {
+    assert!(matches!(from_json(&tags_json(&[tag("past_the_end", 2, 4, "forward")])), Err(AtlasError::Invalid { .. })));
+    assert!(matches!(from_json(&tags_json(&[tag("backwards", 3, 1, "forward")])), Err(AtlasError::Invalid { .. })));
+    assert!(matches!(from_json(&tags_json(&[tag("sideways", 0, 1, "sideways")])), Err(AtlasError::Invalid { .. })));
+}
+
+#[test]
+fn slices_with_a_center_become_nine_slices() {
+    let atlas = from_json(&hash_json(r#""slices": [
+        {"name": "panel", "keys": [
+            {"frame": 2, "bounds": {"x": 1, "y": 1, "w": 14, "h": 14}},
+            {"frame": 0, "bounds": {"x": 0, "y": 0, "w": 16, "h": 16}, "center": {"x": 3, "y": 4, "w": 8, "h": 6}}
+        ]}
+    ]"#)).unwrap();
+
+    // Keys are sorted by frame and carry forward until the next one
+    let slice = atlas.get_slice("panel").unwrap();
+    assert_eq!(slice.get_key(1).unwrap().bounds, Rect::new(0.0, 0.0, 16.0, 16.0));
+    assert_eq!(slice.get_key(3).unwrap().bounds, Rect::new(1.0, 1.0, 14.0, 14.0));
+
+    let nine_slice = atlas.get_nine_slice("panel").unwrap();
+    assert_eq!(nine_slice.frame, "walk 0.aseprite");
+    assert_eq!((nine_slice.insets.left, nine_slice.insets.top, nine_slice.insets.right, nine_slice.insets.bottom), (3.0, 4.0, 5.0, 6.0));
+}
@@ -0,0 +1,31 @@
+#![allow(dead_code)]
+
+use atlas::TextureAtlas;
+use macroquad::{prelude::Rect, texture::Texture2D};
+
+/// One frame of TexturePacker's array layout. `trim` is where the packed pixels sit within the `source` sized sprite,
+/// rotated frames take up `trim` with width and height swapped in the sheet.
+pub fn frame_json(name: &str, x: f32, y: f32, trim: Rect, source: (f32, f32), rotated: bool) -> String {
+    format!(
+        r#"{{"filename": "{}", "frame": {{"x": {}, "y": {}, "w": {}, "h": {}}}, "rotated": {}, "trimmed": {},
+            "spriteSourceSize": {{"x": {}, "y": {}, "w": {}, "h": {}}}, "sourceSize": {{"w": {}, "h": {}}}}}"#,
+        name, x, y, trim.w, trim.h, rotated, trim.w != source.0 || trim.h != source.1,
+        trim.x, trim.y, trim.w, trim.h, source.0, source.1
+    )
+}
+
+/// A frame packed as is at (x, y)
+pub fn untrimmed_json(name: &str, x: f32, y: f32, width: f32, height: f32) -> String {
+    frame_json(name, x, y, Rect::new(0.0, 0.0, width, height), (width, height), false)
+}
+
+/// Wrap frames and any extra meta fields into a whole atlas data file
+pub fn atlas_json(frames: &[String], meta: &str) -> String {
+    let meta = if meta.is_empty() { String::new() } else { format!(", {}", meta) };
+    format!(r#"{{"frames": [{}], "meta": {{"size": {{"w": 256, "h": 256}}{}}}}}"#, frames.join(", "), meta)
+}
+
+/// Tests can't create GL textures, an empty one is enough for anything that doesn't draw
+pub fn atlas_from_json(json: &str) -> TextureAtlas {
+    TextureAtlas::from_json(json, Texture2D::empty()).unwrap_or_else(|err| panic!("Unable to build the atlas: {}", err))
+}
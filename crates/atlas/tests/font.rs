@@ -0,0 +1,187 @@
+mod common;
+
+use atlas::{
+    font::{BitmapFont, TextParams},
+    AtlasError, TextureAtlas,
+};
+use common::{atlas_from_json, atlas_json, frame_json, untrimmed_json};
+use macroquad::prelude::{vec2, Rect, Vec2};
+
+// id, x, y, width, height, xoffset, yoffset, xadvance
+const CHARS: [[i32; 8]; 4] = [
+    [65, 0, 0, 6, 8, 0, 1, 7],
+    [86, 6, 0, 6, 8, 0, 1, 7],
+    [32, 0, 0, 0, 0, 0, 0, 4],
+    [63, 12, 0, 5, 8, 1, 1, 7],
+];
+
+const CHAR_KEYS: [&str; 8] = ["id", "x", "y", "width", "height", "xoffset", "yoffset", "xadvance"];
+
+fn text_fnt() -> String {
+    let mut lines = vec![
+        r#"info face="Press Start 2P" size=8 bold=0"#.to_owned(),
+        "common lineHeight=10 base=8 scaleW=32 scaleH=16 pages=1".to_owned(),
+        r#"page id=0 file="font.png""#.to_owned(),
+        "chars count=4".to_owned(),
+    ];
+    for char in CHARS {
+        // Spacing as wide as BMFont writes it, so values line up in columns
+        lines.push(format!("char {}", CHAR_KEYS.iter().zip(char).map(|(key, value)| format!("{}={:<4}", key, value)).collect::<Vec<_>>().join(" ")));
+    }
+    lines.push("kerning first=65 second=86 amount=-2".to_owned());
+    lines.join("\n")
+}
+
+fn xml_fnt() -> String {
+    let chars: Vec<String> = CHARS.iter().map(|char| {
+        format!("    <char {}/>", CHAR_KEYS.iter().zip(char).map(|(key, value)| format!(r#"{}="{}""#, key, value)).collect::<Vec<_>>().join(" "))
+    }).collect();
+
+    format!(r#"<?xml version="1.0"?>
+<font>
+  <info face="Press Start 2P" size="8"/>
+  <common lineHeight="10" base="8" scaleW="32" scaleH="16" pages="1"/>
+  <pages>
+    <page id="0" file="font.png" />
+  </pages>
+  <chars count="4">
+{}
+  </chars>
+  <kernings count="1">
+    <kerning first="65" second="86" amount="-2"/>
+  </kernings>
+</font>"#, chars.join("\n"))
+}
+
+/// The font's page packed at (64, 32), beside a 48x8 grid font sheet of 8x8 cells
+fn test_atlas() -> TextureAtlas {
+    atlas_from_json(&atlas_json(&[
+        untrimmed_json("font.png", 64.0, 32.0, 32.0, 16.0),
+        untrimmed_json("grid.png", 0.0, 100.0, 48.0, 8.0),
+        frame_json("rotated.png", 0.0, 0.0, Rect::new(0.0, 0.0, 32.0, 16.0), (32.0, 16.0), true),
+    ], ""))
+}
+
+fn bmfont(contents: &str) -> BitmapFont {
+    BitmapFont::from_bmfont_str(contents, &test_atlas(), "font.png").unwrap()
+}
+
+fn max_width(width: f32) -> TextParams {
+    TextParams {
+        max_width: Some(width),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn glyphs_are_offset_by_their_page_frame() {
+    let font = bmfont(&text_fnt());
+    let quads = font.layout("AV", 10.0, 20.0, &TextParams::default());
+
+    // V is kerned 2 pixels closer to the A
+    assert_eq!(quads, vec![
+        (Rect::new(64.0, 32.0, 6.0, 8.0), Rect::new(10.0, 21.0, 6.0, 8.0)),
+        (Rect::new(70.0, 32.0, 6.0, 8.0), Rect::new(15.0, 21.0, 6.0, 8.0)),
+    ]);
+    assert_eq!(font.get_line_height(), 10.0);
+}
+
+#[test]
+fn text_and_xml_fonts_read_the_same() {
+    let (text, xml) = (bmfont(&text_fnt()), bmfont(&xml_fnt()));
+
+    for sample in ["AV", "A V?", "VAVA"] {
+        assert_eq!(text.layout(sample, 0.0, 0.0, &TextParams::default()), xml.layout(sample, 0.0, 0.0, &TextParams::default()));
+    }
+}
+
+#[test]
+fn quoted_attributes_keep_their_spaces() {
+    // The quoted face would otherwise be split into a lineHeight-less mess of attributes
+    let font = bmfont(&text_fnt().replace("common lineHeight=10", r#"common name="a b=c" lineHeight=12"#));
+    assert_eq!(font.get_line_height(), 12.0);
+}
+
+#[test]
+fn missing_glyphs_fall_back_to_a_question_mark() {
+    let font = bmfont(&text_fnt());
+    assert_eq!(font.layout("Z", 0.0, 0.0, &TextParams::default())[0].0, Rect::new(76.0, 32.0, 5.0, 8.0));
+}
+
+#[test]
+fn bad_fonts_are_rejected() {
+    let atlas = test_atlas();
+
+    let two_pages = text_fnt().replace("pages=1", "pages=2");
+    assert!(matches!(BitmapFont::from_bmfont_str(&two_pages, &atlas, "font.png"), Err(AtlasError::Invalid { .. })));
+
+    let outside = text_fnt().replace("char id=63   x=12", "char id=63   x=30");
+    assert!(matches!(BitmapFont::from_bmfont_str(&outside, &atlas, "font.png"), Err(AtlasError::Invalid { .. })));
+
+    let missing_key = text_fnt().replace("xadvance=7", "");
+    assert!(matches!(BitmapFont::from_bmfont_str(&missing_key, &atlas, "font.png"), Err(AtlasError::Invalid { .. })));
+
+    assert!(matches!(BitmapFont::from_bmfont_str(&text_fnt(), &atlas, "rotated.png"), Err(AtlasError::Invalid { .. })));
+    assert!(matches!(BitmapFont::from_bmfont_str(&text_fnt(), &atlas, "missing.png"), Err(AtlasError::UnknownFrame(_))));
+}
+
+#[test]
+fn wrap_breaks_at_spaces_that_would_overflow() {
+    let font = bmfont(&text_fnt());
+
+    // "AV" is 12 wide and a space 4, so "AV AV" is 28
+    assert_eq!(font.measure("AV AV AV", None), vec2(44.0, 10.0));
+    assert_eq!(font.measure("AV AV AV", Some(30.0)), vec2(28.0, 20.0));
+    assert_eq!(font.measure("AV AV AV", Some(20.0)), vec2(12.0, 30.0));
+}
+
+#[test]
+fn wrap_keeps_long_words_whole_and_honours_newlines() {
+    let font = bmfont(&text_fnt());
+
+    assert_eq!(font.measure("AVAVAV", Some(10.0)).y, 10.0);
+    assert_eq!(font.measure("A\nV\n", None).y, 30.0);
+    assert_eq!(font.measure("AV AV\nAV", Some(100.0)), vec2(28.0, 20.0));
+}
+
+#[test]
+fn wrapped_lines_start_back_at_the_left() {
+    let font = bmfont(&text_fnt());
+    let quads = font.layout("A A", 0.0, 0.0, &max_width(10.0));
+
+    assert_eq!(quads.iter().map(|(_, dest)| (dest.x, dest.y)).collect::<Vec<_>>(), vec![(0.0, 1.0), (0.0, 11.0)]);
+}
+
+#[test]
+fn grid_fonts_skip_spaces_without_a_cell() {
+    let font = BitmapFont::from_grid(&test_atlas(), "grid.png", Vec2::splat(8.0), "AB?").unwrap();
+    let quads = font.layout("A B", 0.0, 0.0, &TextParams::default());
+
+    // The space moves the pen a whole cell and draws nothing, rather than a question mark
+    assert_eq!(quads, vec![
+        (Rect::new(0.0, 100.0, 8.0, 8.0), Rect::new(0.0, 0.0, 8.0, 8.0)),
+        (Rect::new(8.0, 100.0, 8.0, 8.0), Rect::new(16.0, 0.0, 8.0, 8.0)),
+    ]);
+    assert_eq!(font.measure("A B", None), vec2(24.0, 8.0));
+}
+
+#[test]
+fn grid_fonts_keep_a_drawn_space() {
+    let font = BitmapFont::from_grid(&test_atlas(), "grid.png", Vec2::splat(8.0), " A").unwrap();
+    assert_eq!(font.layout(" ", 0.0, 0.0, &TextParams::default()), vec![(Rect::new(0.0, 100.0, 8.0, 8.0), Rect::new(0.0, 0.0, 8.0, 8.0))]);
+}
+
+#[test]
+fn grid_fonts_must_fit_their_frame() {
+    assert!(BitmapFont::from_grid(&test_atlas(), "grid.png", Vec2::splat(8.0), "ABCDEFG").is_err());
+}
+
+#[test]
+fn grid_fonts_need_a_positive_glyph_size() {
+    let atlas = test_atlas();
+
+    for glyph_size in [Vec2::ZERO, vec2(0.0, 8.0), vec2(8.0, 0.0), vec2(-8.0, 8.0), vec2(8.0, -8.0)] {
+        assert!(matches!(BitmapFont::from_grid(&atlas, "grid.png", glyph_size, "AB"), Err(AtlasError::Invalid { .. })), "{}", glyph_size);
+        assert!(matches!(BitmapFont::from_grid(&atlas, "grid.png", glyph_size, ""), Err(AtlasError::Invalid { .. })), "{}", glyph_size);
+    }
+}
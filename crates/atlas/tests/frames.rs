@@ -0,0 +1,121 @@
+mod common;
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use atlas::{AtlasAnchor, AtlasTextureParams, FrameQuad};
+use common::{atlas_from_json, atlas_json, frame_json, untrimmed_json};
+use macroquad::prelude::{vec2, Rect, Vec2};
+
+fn test_atlas() -> atlas::TextureAtlas {
+    atlas_from_json(&atlas_json(&[
+        untrimmed_json("plain", 0.0, 0.0, 16.0, 24.0),
+
+        // A 32x32 sprite with only a 20x16 block of pixels 4 in from the left and 6 down from the top
+        frame_json("trimmed", 32.0, 0.0, Rect::new(4.0, 6.0, 20.0, 16.0), (32.0, 32.0), false),
+
+        // A 16x24 sprite turned on its side in the sheet
+        frame_json("rotated", 64.0, 0.0, Rect::new(0.0, 0.0, 16.0, 24.0), (16.0, 24.0), true),
+    ], ""))
+}
+
+fn get_quad(name: &str, position: Vec2, params: AtlasTextureParams) -> FrameQuad {
+    test_atlas().get_quad(name, position.x, position.y, &params).unwrap()
+}
+
+fn assert_near(actual: Vec2, expected: Vec2) {
+    assert!(actual.distance(expected) < 1e-3, "expected {} but got {}", expected, actual);
+}
+
+#[test]
+fn untrimmed_frames_cover_their_sprite() {
+    let quad = get_quad("plain", vec2(10.0, 20.0), AtlasTextureParams::default());
+
+    assert_eq!(quad.region, Rect::new(0.0, 0.0, 16.0, 24.0));
+    assert_near(quad.center, vec2(18.0, 32.0));
+    assert_eq!(quad.size, vec2(16.0, 24.0));
+    assert_eq!(quad.rotation, 0.0);
+}
+
+#[test]
+fn trimmed_frames_keep_their_offset_within_the_sprite() {
+    let quad = get_quad("trimmed", vec2(100.0, 100.0), AtlasTextureParams::default());
+
+    assert_eq!(quad.region, Rect::new(32.0, 0.0, 20.0, 16.0));
+    assert_near(quad.center, vec2(114.0, 114.0));
+    assert_eq!(quad.size, vec2(20.0, 16.0));
+}
+
+#[test]
+fn flipping_mirrors_the_trim_within_the_sprite() {
+    let quad = get_quad("trimmed", vec2(100.0, 100.0), AtlasTextureParams {
+        flip_x: true,
+        flip_y: true,
+        ..Default::default()
+    });
+
+    // 8 pixels of space are left on the right and 10 at the bottom, which become the left and top once flipped
+    assert_near(quad.center, vec2(118.0, 118.0));
+    assert!(quad.flip_x && quad.flip_y);
+}
+
+#[test]
+fn pivot_anchor_places_the_pivot_at_the_position() {
+    let json = atlas_json(&[
+        frame_json("feet", 0.0, 0.0, Rect::new(4.0, 6.0, 20.0, 16.0), (32.0, 32.0), false)
+            .replace(r#""sourceSize""#, r#""pivot": {"x": 0.5, "y": 1.0}, "sourceSize""#),
+    ], "");
+    let quad = atlas_from_json(&json).get_quad("feet", 100.0, 100.0, &AtlasTextureParams {
+        anchor: AtlasAnchor::Pivot,
+        ..Default::default()
+    }).unwrap();
+
+    // The sprite's top left ends up at (84, 68), a pivot at the bottom middle of the 32x32 sprite
+    assert_near(quad.center, vec2(98.0, 82.0));
+}
+
+#[test]
+fn rotation_turns_trimmed_pixels_around_the_sprite_pivot() {
+    let quad = get_quad("trimmed", vec2(100.0, 100.0), AtlasTextureParams {
+        rotation: PI,
+        ..Default::default()
+    });
+
+    // Turning half way round the sprite's center lands the trimmed block where flipping both ways would
+    assert_near(quad.center, vec2(118.0, 118.0));
+    assert_eq!(quad.rotation, PI);
+}
+
+#[test]
+fn rotation_around_a_screen_pivot_moves_the_whole_sprite() {
+    let quad = get_quad("plain", vec2(0.0, 0.0), AtlasTextureParams {
+        rotation: FRAC_PI_2,
+        pivot: Some(Vec2::ZERO),
+        ..Default::default()
+    });
+
+    // The center at (8, 12) turns a quarter clockwise around the origin
+    assert_near(quad.center, vec2(-12.0, 8.0));
+}
+
+#[test]
+fn rotated_frames_are_turned_back_upright() {
+    let quad = get_quad("rotated", vec2(10.0, 20.0), AtlasTextureParams::default());
+
+    // The sheet holds the sprite 24 wide and 16 tall, drawing it a quarter turn back stands it up as 16x24
+    assert_eq!(quad.region, Rect::new(64.0, 0.0, 24.0, 16.0));
+    assert_eq!(quad.size, vec2(24.0, 16.0));
+    assert!((quad.rotation + FRAC_PI_2).abs() < 1e-6);
+    assert_near(quad.center, vec2(18.0, 32.0));
+}
+
+#[test]
+fn rotated_frames_swap_their_flips() {
+    let quad = get_quad("rotated", vec2(0.0, 0.0), AtlasTextureParams {
+        flip_x: true,
+        ..Default::default()
+    });
+
+    // The sprite's X axis runs down the sheet, so mirroring it flips the region vertically
+    assert!(!quad.flip_x);
+    assert!(quad.flip_y);
+}
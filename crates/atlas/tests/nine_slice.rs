@@ -0,0 +1,128 @@
+mod common;
+
+use atlas::{
+    nine_slice::{nine_slices_from_json, NineSlice, NineSliceInsets, NineSliceMode},
+    AtlasError, TextureAtlas,
+};
+use common::{atlas_from_json, atlas_json, frame_json, untrimmed_json};
+use macroquad::prelude::Rect;
+
+const INSETS: NineSliceInsets = NineSliceInsets { left: 4.0, top: 5.0, right: 6.0, bottom: 7.0 };
+
+/// A 24x24 panel at (32, 16), leaving a 14x12 middle between the insets
+fn test_atlas(edge_mode: NineSliceMode, center_mode: NineSliceMode) -> TextureAtlas {
+    let mut atlas = atlas_from_json(&atlas_json(&[untrimmed_json("panel", 32.0, 16.0, 24.0, 24.0)], ""));
+    atlas.add_nine_slice("panel", NineSlice {
+        edge_mode,
+        center_mode,
+        ..NineSlice::new("panel", INSETS)
+    }).unwrap();
+    atlas
+}
+
+fn area(pieces: &[(Rect, Rect)]) -> f32 {
+    pieces.iter().map(|(_, dest)| dest.w * dest.h).sum()
+}
+
+#[test]
+fn stretched_pieces_keep_their_borders_and_fill_the_rest() {
+    let atlas = test_atlas(NineSliceMode::Stretch, NineSliceMode::Stretch);
+    let pieces = atlas.get_nine_slice_pieces("panel", Rect::new(100.0, 200.0, 60.0, 40.0)).unwrap();
+
+    assert_eq!(pieces, vec![
+        (Rect::new(32.0, 16.0, 4.0, 5.0), Rect::new(100.0, 200.0, 4.0, 5.0)),
+        (Rect::new(36.0, 16.0, 14.0, 5.0), Rect::new(104.0, 200.0, 50.0, 5.0)),
+        (Rect::new(50.0, 16.0, 6.0, 5.0), Rect::new(154.0, 200.0, 6.0, 5.0)),
+        (Rect::new(32.0, 21.0, 4.0, 12.0), Rect::new(100.0, 205.0, 4.0, 28.0)),
+        (Rect::new(36.0, 21.0, 14.0, 12.0), Rect::new(104.0, 205.0, 50.0, 28.0)),
+        (Rect::new(50.0, 21.0, 6.0, 12.0), Rect::new(154.0, 205.0, 6.0, 28.0)),
+        (Rect::new(32.0, 33.0, 4.0, 7.0), Rect::new(100.0, 233.0, 4.0, 7.0)),
+        (Rect::new(36.0, 33.0, 14.0, 7.0), Rect::new(104.0, 233.0, 50.0, 7.0)),
+        (Rect::new(50.0, 33.0, 6.0, 7.0), Rect::new(154.0, 233.0, 6.0, 7.0)),
+    ]);
+}
+
+#[test]
+fn tiled_edges_repeat_and_cut_the_last_copy_short() {
+    let atlas = test_atlas(NineSliceMode::Tile, NineSliceMode::Stretch);
+    let dest = Rect::new(0.0, 0.0, 40.0, 24.0);
+    let pieces = atlas.get_nine_slice_pieces("panel", dest).unwrap();
+
+    // 30 pixels of top edge take two whole 14 pixel copies and 2 pixels of a third
+    let top_edge: Vec<_> = pieces.iter().filter(|(_, piece)| piece.y == 0.0 && piece.x >= 4.0 && piece.right() <= 34.0).collect();
+    assert_eq!(top_edge.iter().map(|(region, piece)| (region.x, region.w, piece.x, piece.w)).collect::<Vec<_>>(), vec![
+        (36.0, 14.0, 4.0, 14.0),
+        (36.0, 14.0, 18.0, 14.0),
+        (36.0, 2.0, 32.0, 2.0),
+    ]);
+
+    // Corners and the stretched center stay single pieces, the sides fit their 12 pixels exactly once
+    assert_eq!(pieces.len(), 13);
+    assert_eq!(area(&pieces), dest.w * dest.h);
+}
+
+#[test]
+fn tiled_centers_repeat_both_ways() {
+    let atlas = test_atlas(NineSliceMode::Stretch, NineSliceMode::Tile);
+    let pieces = atlas.get_nine_slice_pieces("panel", Rect::new(0.0, 0.0, 4.0 + 28.0 + 6.0, 5.0 + 18.0 + 7.0)).unwrap();
+
+    // Two copies across and one and a half down
+    let center: Vec<_> = pieces.iter().filter(|(_, piece)| piece.x >= 4.0 && piece.right() <= 32.0 && piece.y >= 5.0 && piece.bottom() <= 23.0).collect();
+    assert_eq!(center.len(), 4);
+    assert!(center.iter().all(|(region, piece)| region.size() == piece.size()));
+}
+
+#[test]
+fn small_destinations_shrink_the_borders_evenly() {
+    let atlas = test_atlas(NineSliceMode::Stretch, NineSliceMode::Stretch);
+    let pieces = atlas.get_nine_slice_pieces("panel", Rect::new(0.0, 0.0, 5.0, 24.0)).unwrap();
+
+    // Half the width for each side border, nothing left over for the middle column
+    assert_eq!(pieces.len(), 6);
+    assert!(pieces.iter().all(|(_, piece)| piece.w == 2.0 || piece.w == 3.0));
+    assert_eq!(area(&pieces), 5.0 * 24.0);
+}
+
+#[test]
+fn empty_destinations_have_no_pieces() {
+    let atlas = test_atlas(NineSliceMode::Tile, NineSliceMode::Tile);
+    assert!(atlas.get_nine_slice_pieces("panel", Rect::new(0.0, 0.0, 0.0, 0.0)).unwrap().is_empty());
+}
+
+#[test]
+fn insets_must_fit_the_frame() {
+    let mut atlas = atlas_from_json(&atlas_json(&[untrimmed_json("small", 0.0, 0.0, 8.0, 8.0)], ""));
+    let result = atlas.add_nine_slice("small", NineSlice::new("small", NineSliceInsets { left: 5.0, top: 1.0, right: 5.0, bottom: 1.0 }));
+    assert!(matches!(result, Err(AtlasError::Invalid { .. })));
+}
+
+#[test]
+fn rotated_and_trimmed_frames_are_rejected() {
+    let mut atlas = atlas_from_json(&atlas_json(&[
+        frame_json("rotated", 0.0, 0.0, Rect::new(0.0, 0.0, 16.0, 16.0), (16.0, 16.0), true),
+        frame_json("trimmed", 16.0, 0.0, Rect::new(2.0, 2.0, 12.0, 12.0), (16.0, 16.0), false),
+    ], ""));
+
+    assert!(matches!(atlas.add_nine_slice("rotated", NineSlice::new("rotated", INSETS)), Err(AtlasError::Invalid { .. })));
+    assert!(matches!(atlas.add_nine_slice("trimmed", NineSlice::new("trimmed", INSETS)), Err(AtlasError::Invalid { .. })));
+    assert!(matches!(atlas.add_nine_slice("missing", NineSlice::new("missing", INSETS)), Err(AtlasError::UnknownFrame(_))));
+
+    // Bounds inside the trimmed pixels are fine
+    let inside = NineSlice {
+        bounds: Some(Rect::new(2.0, 2.0, 12.0, 12.0)),
+        ..NineSlice::new("trimmed", NineSliceInsets { left: 3.0, top: 3.0, right: 3.0, bottom: 3.0 })
+    };
+    atlas.add_nine_slice("trimmed", inside).unwrap();
+    assert_eq!(atlas.get_nine_slice_pieces("trimmed", Rect::new(0.0, 0.0, 12.0, 12.0)).unwrap()[0].0, Rect::new(16.0, 0.0, 3.0, 3.0));
+}
+
+#[test]
+fn sidecar_nine_slices_read_their_modes() {
+    let nine_slices = nine_slices_from_json(r#"{"nine_slices": {"panel": {"left": 1, "top": 2, "right": 3, "bottom": 4, "edges": "tile"}}}"#).unwrap();
+
+    assert_eq!(nine_slices, vec![NineSlice {
+        edge_mode: NineSliceMode::Tile,
+        ..NineSlice::new("panel", NineSliceInsets { left: 1.0, top: 2.0, right: 3.0, bottom: 4.0 })
+    }]);
+    assert!(nine_slices_from_json(r#"{"nine_slices": {"panel": {"left": 1, "top": 1, "right": 1, "bottom": 1, "center": "wobble"}}}"#).is_err());
+}
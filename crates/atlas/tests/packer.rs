@@ -0,0 +1,134 @@
+mod common;
+
+use atlas::packer::{PackedAtlas, TexturePacker};
+use common::atlas_from_json;
+use image::{Rgba, RgbaImage};
+use macroquad::{prelude::{vec2, Rect}, rand::RandGenerator};
+
+const PADDING: u32 = 2;
+const EXTRUDE: u32 = 1;
+
+/// An image where every pixel is distinct, so a misplaced copy or extrusion shows up
+fn pattern(id: u8, width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| Rgba([id, x as u8, y as u8, 255]))
+}
+
+/// A mix of sizes from slivers to large blocks, the same every run
+fn random_images(seed: u64, count: usize) -> Vec<(String, RgbaImage)> {
+    let rng = RandGenerator::new();
+    rng.srand(seed);
+
+    (0..count).map(|index| {
+        let (width, height) = (rng.gen_range(1, 48), rng.gen_range(1, 48));
+        (format!("image_{}.png", index), pattern(index as u8, width, height))
+    }).collect()
+}
+
+fn pack(images: &[(String, RgbaImage)]) -> PackedAtlas {
+    let mut packer = TexturePacker::new();
+    packer.set_padding(PADDING);
+    packer.set_extrude(EXTRUDE);
+    for (name, image) in images {
+        packer.add_image(name, image.clone());
+    }
+    packer.pack("packed.png").unwrap()
+}
+
+/// The region with its extruded border, which is what has to stay clear of other images
+fn extruded(region: Rect) -> Rect {
+    let amount = EXTRUDE as f32;
+    Rect::new(region.x - amount, region.y - amount, region.w + amount * 2.0, region.h + amount * 2.0)
+}
+
+#[test]
+fn images_keep_their_padding_and_never_overlap() {
+    for seed in 0..8 {
+        let images = random_images(seed, 40);
+        let packed = pack(&images);
+        let regions: Vec<Rect> = images.iter().map(|(name, _)| extruded(packed.get_frame_region(name).unwrap())).collect();
+
+        for (index, first) in regions.iter().enumerate() {
+            for second in regions[index + 1..].iter() {
+                // Separated along at least one axis by at least the padding
+                let gap_x = (second.x - first.right()).max(first.x - second.right());
+                let gap_y = (second.y - first.bottom()).max(first.y - second.bottom());
+                assert!(gap_x.max(gap_y) >= PADDING as f32, "seed {}: {:?} and {:?} are too close", seed, first, second);
+            }
+        }
+    }
+}
+
+#[test]
+fn images_fit_inside_the_texture() {
+    let images = random_images(100, 60);
+    let packed = pack(&images);
+    let (width, height) = packed.get_image().dimensions();
+    assert!(width.is_power_of_two() && height.is_power_of_two());
+
+    for (name, _) in images.iter() {
+        let region = extruded(packed.get_frame_region(name).unwrap());
+        assert!(region.x >= 0.0 && region.y >= 0.0 && region.right() <= width as f32 && region.bottom() <= height as f32, "{} is outside the texture", name);
+    }
+}
+
+#[test]
+fn pixels_are_copied_and_edges_extruded() {
+    let images = random_images(7, 20);
+    let packed = pack(&images);
+    let image = packed.get_image();
+
+    let mut covered = RgbaImage::new(image.width(), image.height());
+    for (name, source) in images.iter() {
+        let region = packed.get_frame_region(name).unwrap();
+        let (left, top) = (region.x as i64, region.y as i64);
+        let (width, height) = source.dimensions();
+
+        // Every pixel in and around the image should match the nearest pixel of the source
+        let amount = EXTRUDE as i64;
+        for y in top - amount..top + height as i64 + amount {
+            for x in left - amount..left + width as i64 + amount {
+                let source_x = (x - left).clamp(0, width as i64 - 1) as u32;
+                let source_y = (y - top).clamp(0, height as i64 - 1) as u32;
+                assert_eq!(image.get_pixel(x as u32, y as u32), source.get_pixel(source_x, source_y), "{} at ({}, {})", name, x, y);
+                covered.put_pixel(x as u32, y as u32, Rgba([1, 0, 0, 0]));
+            }
+        }
+    }
+
+    // Everything else, padding included, is left empty
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if covered.get_pixel(x, y).0[0] == 0 {
+            assert_eq!(pixel.0, [0, 0, 0, 0], "stray pixel at ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn packed_json_reads_back_as_an_atlas() {
+    let images = random_images(3, 10);
+    let packed = pack(&images);
+    let atlas = atlas_from_json(&packed.to_json());
+
+    for (name, source) in images.iter() {
+        assert_eq!(atlas.get_frame_size(name).unwrap(), vec2(source.width() as f32, source.height() as f32));
+        assert_eq!(atlas.get_quad(name, 0.0, 0.0, &Default::default()).unwrap().region, packed.get_frame_region(name).unwrap());
+    }
+}
+
+#[test]
+fn images_larger_than_the_maximum_size_fail() {
+    let mut packer = TexturePacker::new();
+    packer.set_max_size(64);
+    packer.add_image("big.png", pattern(0, 100, 10));
+    assert!(packer.pack("packed.png").is_err());
+}
+
+#[test]
+fn duplicate_names_and_empty_packers_fail() {
+    assert!(TexturePacker::new().pack("packed.png").is_err());
+
+    let mut packer = TexturePacker::new();
+    packer.add_image("same.png", pattern(0, 4, 4));
+    packer.add_image("same.png", pattern(1, 4, 4));
+    assert!(packer.pack("packed.png").is_err());
+}
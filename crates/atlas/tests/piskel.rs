@@ -0,0 +1,149 @@
+use std::io::Cursor;
+
+use atlas::{piskel::{decode_base64, parse_piskel}, AtlasError};
+use image::{ImageOutputFormat, Rgba, RgbaImage};
+
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |buffer, (index, byte)| buffer | (*byte as u32) << (16 - index * 8));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(buffer >> (18 - index * 6) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn data_url(image: &RgbaImage) -> String {
+    let mut bytes = Vec::new();
+    image.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png).unwrap();
+    format!("data:image/png;base64,{}", encode_base64(&bytes))
+}
+
+/// A strip of `colors.len()` frames, 2x2 each, filled with one color per frame
+fn strip(colors: &[Rgba<u8>]) -> RgbaImage {
+    RgbaImage::from_fn(colors.len() as u32 * 2, 2, |x, _| colors[x as usize / 2])
+}
+
+/// A piskel file with each layer given as its raw JSON, which the file stores as a string
+fn piskel_json(model_version: u32, layers: &[String]) -> String {
+    let layers: Vec<String> = layers.iter().map(|layer| format!("{:?}", layer)).collect();
+    format!(
+        r#"{{"modelVersion": {}, "piskel": {{"name": "blob", "fps": 8, "width": 2, "height": 2, "layers": [{}]}}}}"#,
+        model_version, layers.join(", ")
+    )
+}
+
+/// A version 2 layer with every frame in one chunk, laid out left to right
+fn chunk_layer(colors: &[Rgba<u8>], opacity: f32) -> String {
+    let layout: Vec<String> = (0..colors.len()).map(|frame| format!("[{}]", frame)).collect();
+    format!(
+        r#"{{"name": "layer", "opacity": {}, "frameCount": {}, "chunks": [{{"layout": [{}], "base64PNG": "{}"}}]}}"#,
+        opacity, colors.len(), layout.join(", "), data_url(&strip(colors))
+    )
+}
+
+#[test]
+fn base64_decodes_with_and_without_padding() {
+    assert_eq!(decode_base64("TWFu").unwrap(), b"Man");
+    assert_eq!(decode_base64("TWE=").unwrap(), b"Ma");
+    assert_eq!(decode_base64("TQ==").unwrap(), b"M");
+    assert_eq!(decode_base64("").unwrap(), b"");
+}
+
+#[test]
+fn base64_skips_whitespace() {
+    assert_eq!(decode_base64("TW\nFu\r\n IGFu ZA==").unwrap(), b"Man and");
+}
+
+#[test]
+fn base64_rejects_other_characters() {
+    assert!(decode_base64("TW*u").is_err());
+}
+
+#[test]
+fn base64_round_trips_every_byte() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    for length in [254, 255, 256] {
+        assert_eq!(decode_base64(&encode_base64(&bytes[..length])).unwrap(), &bytes[..length]);
+    }
+}
+
+#[test]
+fn chunked_layers_split_into_frames() {
+    let sprite = parse_piskel(&piskel_json(2, &[chunk_layer(&[RED, BLUE, CLEAR], 1.0)])).unwrap();
+
+    assert_eq!(sprite.name, "blob");
+    assert_eq!(sprite.fps, 8.0);
+    assert_eq!(sprite.frames.len(), 3);
+    assert!(sprite.frames.iter().all(|frame| frame.dimensions() == (2, 2)));
+    assert_eq!(*sprite.frames[0].get_pixel(1, 1), RED);
+    assert_eq!(*sprite.frames[1].get_pixel(0, 0), BLUE);
+    assert_eq!(*sprite.frames[2].get_pixel(0, 1), CLEAR);
+}
+
+#[test]
+fn chunk_layouts_place_frames_by_column_and_row() {
+    // Two columns of two, holding frames 3 and 0 on the left and 1 and 2 on the right
+    let image = RgbaImage::from_fn(4, 4, |x, y| match (x / 2, y / 2) {
+        (0, 0) => Rgba([3, 0, 0, 255]),
+        (0, 1) => Rgba([0, 0, 0, 255]),
+        (1, 0) => Rgba([1, 0, 0, 255]),
+        _ => Rgba([2, 0, 0, 255]),
+    });
+    let layer = format!(r#"{{"frameCount": 4, "chunks": [{{"layout": [[3, 0], [1, 2]], "base64PNG": "{}"}}]}}"#, data_url(&image));
+    let sprite = parse_piskel(&piskel_json(2, &[layer])).unwrap();
+
+    for (index, frame) in sprite.frames.iter().enumerate() {
+        assert_eq!(frame.get_pixel(0, 0).0[0] as usize, index);
+    }
+}
+
+#[test]
+fn version_one_layers_hold_a_single_strip() {
+    let layer = format!(r#"{{"frameCount": 2, "base64PNG": "{}"}}"#, data_url(&strip(&[BLUE, RED])));
+    let sprite = parse_piskel(&piskel_json(1, &[layer])).unwrap();
+
+    assert_eq!(*sprite.frames[0].get_pixel(0, 0), BLUE);
+    assert_eq!(*sprite.frames[1].get_pixel(0, 0), RED);
+}
+
+#[test]
+fn layers_blend_bottom_to_top_with_their_opacity() {
+    let sprite = parse_piskel(&piskel_json(2, &[
+        chunk_layer(&[RED, RED], 1.0),
+        chunk_layer(&[BLUE, CLEAR], 0.5),
+    ])).unwrap();
+
+    // Half of the blue lands on the red, where the top layer is empty the red shows through untouched
+    let blended = sprite.frames[0].get_pixel(0, 0).0;
+    assert!(blended[0] > 100 && blended[0] < 155 && blended[2] > 100 && blended[2] < 155, "got {:?}", blended);
+    assert_eq!(*sprite.frames[1].get_pixel(0, 0), RED);
+}
+
+#[test]
+fn layers_must_agree_on_the_frame_count() {
+    let result = parse_piskel(&piskel_json(2, &[chunk_layer(&[RED, RED], 1.0), chunk_layer(&[BLUE], 1.0)]));
+    assert!(matches!(result, Err(AtlasError::Invalid { .. })));
+}
+
+#[test]
+fn frames_outside_their_chunk_are_rejected() {
+    let layer = format!(r#"{{"frameCount": 2, "chunks": [{{"layout": [[0, 1]], "base64PNG": "{}"}}]}}"#, data_url(&strip(&[RED, BLUE])));
+    assert!(matches!(parse_piskel(&piskel_json(2, &[layer])), Err(AtlasError::Invalid { .. })));
+}
+
+#[test]
+fn newer_model_versions_are_rejected() {
+    assert!(matches!(parse_piskel(&piskel_json(3, &[chunk_layer(&[RED], 1.0)])), Err(AtlasError::Invalid { .. })));
+}
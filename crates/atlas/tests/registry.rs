@@ -0,0 +1,82 @@
+mod common;
+
+use atlas::{animation::{AnimationClip, AnimationMode}, registry::AtlasRegistry, AtlasError, TextureAtlas};
+use common::{atlas_from_json, atlas_json, untrimmed_json};
+use macroquad::prelude::vec2;
+
+/// An atlas of 8x8 frames, `size` wide each so the atlases can be told apart
+fn test_atlas(names: &[&str], size: f32) -> TextureAtlas {
+    let frames: Vec<String> = names.iter().enumerate().map(|(index, name)| untrimmed_json(name, index as f32 * size, 0.0, size, size)).collect();
+    atlas_from_json(&atlas_json(&frames, ""))
+}
+
+#[test]
+fn namespaces_keep_frames_with_the_same_name_apart() {
+    let mut registry = AtlasRegistry::new();
+    registry.insert("player", test_atlas(&["idle.png"], 8.0)).unwrap();
+    registry.insert("enemy", test_atlas(&["idle.png"], 16.0)).unwrap();
+
+    let player = registry.get_handle("player/idle.png").unwrap();
+    let enemy = registry.get_handle("enemy/idle.png").unwrap();
+    assert_ne!(player, enemy);
+    assert_eq!(registry.get_handle_size(player), vec2(8.0, 8.0));
+    assert_eq!(registry.get_handle_size(enemy), vec2(16.0, 16.0));
+}
+
+#[test]
+fn frame_names_keep_their_own_slashes() {
+    let mut registry = AtlasRegistry::new();
+    registry.insert("ui", test_atlas(&["buttons/ok.png", "ok.png"], 8.0)).unwrap();
+
+    assert!(registry.get_handle("ui/buttons/ok.png").is_ok());
+    assert_ne!(registry.get_handle("ui/buttons/ok.png").unwrap(), registry.get_handle("ui/ok.png").unwrap());
+    assert!(matches!(registry.get_handle("buttons/ok.png"), Err(AtlasError::UnknownFrame(_))));
+    assert!(matches!(registry.get_handle("ui/buttons"), Err(AtlasError::UnknownFrame(_))));
+}
+
+#[test]
+fn namespaces_cannot_be_taken_twice() {
+    let mut registry = AtlasRegistry::new();
+    registry.insert("tiles", test_atlas(&["grass.png"], 8.0)).unwrap();
+
+    assert!(matches!(registry.insert("tiles", test_atlas(&["water.png"], 8.0)), Err(AtlasError::Invalid { .. })));
+
+    // The first atlas is still there, and nothing from the second leaked in
+    assert!(registry.get_handle("tiles/grass.png").is_ok());
+    assert!(registry.get_handle("tiles/water.png").is_err());
+    assert_eq!(registry.get_pages("tiles").count(), 1);
+}
+
+#[test]
+fn namespaces_must_be_a_single_non_empty_name() {
+    let mut registry = AtlasRegistry::new();
+
+    assert!(registry.insert("", test_atlas(&["a.png"], 8.0)).is_err());
+    assert!(registry.insert("ui/menus", test_atlas(&["a.png"], 8.0)).is_err());
+    assert!(!registry.has_namespace("ui/menus"));
+    assert!(registry.get_handle("ui/menus/a.png").is_err());
+}
+
+#[test]
+fn namespace_handles_resolve_plain_frame_names() {
+    let mut registry = AtlasRegistry::new();
+    registry.insert("tiles", test_atlas(&["grass.png", "water.png"], 8.0)).unwrap();
+
+    let handles = registry.get_namespace_handles("tiles", ["water.png", "grass.png"]).unwrap();
+    assert_eq!(handles, registry.get_handles(["tiles/water.png", "tiles/grass.png"]).unwrap());
+    assert!(registry.get_namespace_handles("tiles", ["sand.png"]).is_err());
+}
+
+#[test]
+fn clips_are_found_on_their_page() {
+    let mut atlas = test_atlas(&["walk_0.png", "walk_1.png"], 8.0);
+    atlas.add_clip("walk", AnimationClip::new_with_frames(AnimationMode::Loop, Vec::new())).unwrap();
+
+    let mut registry = AtlasRegistry::new();
+    registry.insert("player", atlas).unwrap();
+
+    assert!(registry.find_clip_page("player", "walk").is_some());
+    assert!(registry.find_clip_page("player", "run").is_none());
+    assert!(registry.find_clip_page("enemy", "walk").is_none());
+    assert!(registry.get_atlas("player").is_some());
+}
@@ -0,0 +1,222 @@
+use macroquad::prelude::{Camera2D, Mat2, Rect, Vec2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+
+    /// Start slow and speed up
+    EaseIn,
+
+    /// Start fast and slow down
+    EaseOut,
+
+    /// Slow at both ends
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map linear progress from 0 to 1 onto the curve
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// A value animating from one number to another over time
+#[derive(Debug, Clone, Copy)]
+struct Tween {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+    duration: f32,
+    easing: Easing,
+}
+
+impl Tween {
+    fn new(value: f32) -> Self {
+        Tween {
+            from: value,
+            to: value,
+            elapsed: 0.0,
+            duration: 0.0,
+            easing: Easing::Linear,
+        }
+    }
+
+    fn get_value(&self) -> f32 {
+        if self.elapsed >= self.duration {
+            return self.to;
+        }
+        self.from + (self.to - self.from) * self.easing.apply(self.elapsed / self.duration)
+    }
+
+    /// Head for a new value, starting from wherever the current animation has got to
+    fn start(&mut self, to: f32, duration: f32, easing: Easing) {
+        *self = Tween {
+            from: self.get_value(),
+            to,
+            elapsed: 0.0,
+            duration,
+            easing,
+        };
+    }
+
+    fn update(&mut self, delta: f32) {
+        self.elapsed = (self.elapsed + delta).min(self.duration);
+    }
+}
+
+/// How strong screen shake gets at full trauma, and how long it lasts
+#[derive(Debug, Clone, Copy)]
+pub struct ShakeParams {
+    /// Furthest the view moves, in world units
+    pub max_offset: Vec2,
+
+    /// Furthest the view turns, in degrees
+    pub max_rotation: f32,
+
+    /// How quickly the shake wobbles, in changes per second
+    pub frequency: f32,
+
+    /// Trauma lost per second
+    pub decay: f32,
+}
+
+impl Default for ShakeParams {
+    fn default() -> Self {
+        ShakeParams {
+            max_offset: Vec2::new(8.0, 8.0),
+            max_rotation: 2.0,
+            frequency: 25.0,
+            decay: 1.5,
+        }
+    }
+}
+
+/// Smooth noise between -1 and 1, a different curve for each seed
+fn noise(seed: f32, time: f32) -> f32 {
+    let hash = |n: f32| ((n * 12.9898 + seed * 78.233).sin() * 43758.547).rem_euclid(1.0) * 2.0 - 1.0;
+
+    let cell = time.floor();
+    let t = time - cell;
+    let (start, end) = (hash(cell), hash(cell + 1.0));
+    start + (end - start) * t * t * (3.0 - 2.0 * t)
+}
+
+/// Shake, zoom and rotation layered over a camera without moving the camera itself,
+/// so a follow controller can keep working underneath
+#[derive(Debug, Clone)]
+pub struct CameraEffects {
+    shake: ShakeParams,
+
+    /// Shake strength from 0 to 1, squared when applied so small knocks stay subtle
+    trauma: f32,
+    time: f32,
+
+    zoom: Tween,
+
+    // Point in the world the zoom closes in on, the middle of the view when `None`
+    zoom_point: Option<Vec2>,
+    rotation: Tween,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        CameraEffects {
+            shake: ShakeParams::default(),
+            trauma: 0.0,
+            time: 0.0,
+            zoom: Tween::new(1.0),
+            zoom_point: None,
+            rotation: Tween::new(0.0),
+        }
+    }
+}
+
+impl CameraEffects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_shake_params(&mut self, shake: ShakeParams) {
+        self.shake = shake;
+    }
+
+    /// Knock the camera, e.g. 0.3 for a bump and 1.0 for a crash. Trauma is capped at 1.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn get_trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Animate to a zoom level, where 2 shows everything twice the size, keeping `point` in the world still on screen
+    pub fn zoom_to(&mut self, zoom: f32, point: Option<Vec2>, duration: f32, easing: Easing) {
+        self.zoom.start(zoom, duration, easing);
+        self.zoom_point = point;
+    }
+
+    /// Animate to a rotation around the middle of the view, in degrees
+    pub fn rotate_to(&mut self, rotation: f32, duration: f32, easing: Easing) {
+        self.rotation.start(rotation, duration, easing);
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom.get_value()
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation.get_value()
+    }
+
+    /// Drop every effect straight away
+    pub fn reset(&mut self) {
+        *self = CameraEffects {
+            shake: self.shake,
+            ..Default::default()
+        };
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+        self.trauma = (self.trauma - self.shake.decay * delta).max(0.0);
+        self.zoom.update(delta);
+        self.rotation.update(delta);
+    }
+
+    /// The camera as it should be drawn with, given the camera underneath and the area of the world it shows
+    pub fn apply(&self, camera: &Camera2D, view: Rect) -> Camera2D {
+        let shake = self.trauma * self.trauma;
+        let time = self.time * self.shake.frequency;
+        let shake_offset = Vec2::new(noise(1.0, time), noise(2.0, time)) * self.shake.max_offset * shake;
+        let shake_rotation = noise(3.0, time) * self.shake.max_rotation * shake;
+
+        let zoom = self.zoom.get_value();
+        let rotation = (self.rotation.get_value() + shake_rotation).to_radians();
+        if zoom == 1.0 && rotation == 0.0 && shake_offset == Vec2::ZERO {
+            return *camera;
+        }
+
+        // Express the effects as a transform of the world, rotating around the middle of the view,
+        // scaling around the zoom point, then shaking, and fold it into the camera's own target, zoom and rotation
+        let center = view.center();
+        let zoom_point = self.zoom_point.unwrap_or(center);
+        let turn = Mat2::from_angle(rotation);
+
+        let matrix = turn * zoom;
+        let translation = zoom * (center - turn * center) + (zoom_point - zoom * zoom_point) + shake_offset;
+
+        let mut result = *camera;
+        result.target = matrix.inverse() * (camera.target - translation);
+        result.zoom = camera.zoom * zoom;
+        result.rotation = camera.rotation + rotation.to_degrees();
+        result
+    }
+}
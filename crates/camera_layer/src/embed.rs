@@ -0,0 +1,121 @@
+use macroquad::{
+    models::{draw_mesh, Mesh, Vertex},
+    prelude::{vec2, vec3, Color, Mat3, Rect, Vec2},
+    texture::Texture2D,
+};
+
+// Rows and columns a perspective quad is cut into when drawn, as triangles can only stretch a texture evenly
+const PERSPECTIVE_SUBDIVISIONS: u16 = 16;
+
+const EDGE_TOLERANCE: f32 = 1e-4;
+
+/// Where a layer is shown inside another layer, as four corners in the host layer's world.
+/// The corners can be any convex shape, e.g. a screen seen at an angle.
+#[derive(Debug, Clone, Copy)]
+pub struct LayerQuad {
+    corners: [Vec2; 4],
+
+    // Maps positions across the layer, 0 to 1 on each axis, to the host's world
+    homography: Mat3,
+}
+
+impl LayerQuad {
+    /// Corners go clockwise from the one the layer's top left is drawn at
+    pub fn new(top_left: Vec2, top_right: Vec2, bottom_right: Vec2, bottom_left: Vec2) -> Self {
+        LayerQuad {
+            corners: [top_left, top_right, bottom_right, bottom_left],
+            homography: square_to_quad([top_left, top_right, bottom_right, bottom_left]),
+        }
+    }
+
+    pub fn from_rect(rect: Rect) -> Self {
+        Self::new(
+            vec2(rect.left(), rect.top()),
+            vec2(rect.right(), rect.top()),
+            vec2(rect.right(), rect.bottom()),
+            vec2(rect.left(), rect.bottom()),
+        )
+    }
+
+    pub fn get_corners(&self) -> [Vec2; 4] {
+        self.corners
+    }
+
+    /// Whether the quad is a parallelogram, so it can be drawn without perspective correction
+    pub fn is_affine(&self) -> bool {
+        self.homography.x_axis.z == 0.0 && self.homography.y_axis.z == 0.0
+    }
+
+    /// A position across the layer, from (0, 0) at its top left to (1, 1) at its bottom right, in the host's world
+    pub fn to_host(&self, uv: Vec2) -> Vec2 {
+        let point = self.homography * vec3(uv.x, uv.y, 1.0);
+        vec2(point.x, point.y) / point.z
+    }
+
+    /// Where a point in the host's world lands across the layer, `None` when it's outside the quad
+    pub fn to_uv(&self, point: Vec2) -> Option<Vec2> {
+        let uv = self.homography.inverse() * vec3(point.x, point.y, 1.0);
+        if uv.z.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        // Let points right on the edge through, rounding can put them a hair outside
+        let uv = vec2(uv.x, uv.y) / uv.z;
+        let inside = uv.cmpge(Vec2::splat(-EDGE_TOLERANCE)).all() && uv.cmple(Vec2::splat(1.0 + EDGE_TOLERANCE)).all();
+        inside.then(|| uv.clamp(Vec2::ZERO, Vec2::ONE))
+    }
+
+    /// Draw a texture stretched over the quad, using the current camera and material
+    pub fn draw_texture(&self, texture: Texture2D, color: Color) {
+        let subdivisions = if self.is_affine() { 1 } else { PERSPECTIVE_SUBDIVISIONS };
+
+        let mut vertices = Vec::with_capacity((subdivisions as usize + 1).pow(2));
+        for row in 0..=subdivisions {
+            for column in 0..=subdivisions {
+                let uv = vec2(column as f32, row as f32) / subdivisions as f32;
+                vertices.push(Vertex {
+                    position: self.to_host(uv).extend(0.0),
+                    uv,
+                    color,
+                });
+            }
+        }
+
+        let mut indices = Vec::with_capacity(subdivisions as usize * subdivisions as usize * 6);
+        for row in 0..subdivisions {
+            for column in 0..subdivisions {
+                let top_left = row * (subdivisions + 1) + column;
+                let bottom_left = top_left + subdivisions + 1;
+                indices.extend_from_slice(&[top_left, top_left + 1, bottom_left + 1, top_left, bottom_left + 1, bottom_left]);
+            }
+        }
+
+        draw_mesh(&Mesh {
+            vertices,
+            indices,
+            texture: Some(texture),
+        });
+    }
+}
+
+/// The projective transform taking the unit square's corners to the quad's, after Heckbert's
+/// "Fundamentals of Texture Mapping and Image Warping"
+fn square_to_quad(corners: [Vec2; 4]) -> Mat3 {
+    let [first, second, third, fourth] = corners;
+    let sum = first - second + third - fourth;
+
+    let (g, h) = if sum == Vec2::ZERO {
+        (0.0, 0.0)
+    }
+    else {
+        let across = second - third;
+        let down = fourth - third;
+        let denominator = across.perp_dot(down);
+        (sum.perp_dot(down) / denominator, across.perp_dot(sum) / denominator)
+    };
+
+    let u = second - first + second * g;
+    let v = fourth - first + fourth * h;
+
+    Mat3::from_cols(u.extend(g), v.extend(h), first.extend(1.0))
+}
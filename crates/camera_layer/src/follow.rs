@@ -0,0 +1,124 @@
+use macroquad::prelude::{Rect, Vec2};
+
+/// Keeps a `CameraLayer` on a moving target, see `CameraLayer::update_follow`
+#[derive(Debug, Clone)]
+pub struct CameraFollow {
+    /// Area around the center of the view, in world units, that the target can move in without the camera following
+    deadzone: Rect,
+
+    /// How quickly the camera catches up, higher is faster. Zero snaps straight to the target.
+    smoothing: f32,
+
+    /// Seconds of the target's velocity to look ahead by
+    lookahead: f32,
+
+    /// Area of the world the view has to stay inside
+    bounds: Option<Rect>,
+
+    /// Round the camera to whole world units so pixel art doesn't swim as it scrolls
+    pixel_snap: bool,
+
+    // Unsnapped center of the view, so smoothing still creeps along when snapping would round each step away
+    center: Option<Vec2>,
+}
+
+impl CameraFollow {
+    pub fn new(deadzone: Rect) -> Self {
+        CameraFollow {
+            deadzone,
+            smoothing: 0.0,
+            lookahead: 0.0,
+            bounds: None,
+            pixel_snap: false,
+            center: None,
+        }
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: Rect) {
+        self.deadzone = deadzone;
+    }
+
+    pub fn set_smoothing(&mut self, smoothing: f32) {
+        self.smoothing = smoothing;
+    }
+
+    pub fn set_lookahead(&mut self, lookahead: f32) {
+        self.lookahead = lookahead;
+    }
+
+    pub fn set_bounds(&mut self, bounds: Option<Rect>) {
+        self.bounds = bounds;
+    }
+
+    pub fn set_pixel_snap(&mut self, pixel_snap: bool) {
+        self.pixel_snap = pixel_snap;
+    }
+
+    pub fn get_deadzone(&self) -> Rect {
+        self.deadzone
+    }
+
+    pub fn get_bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+
+    /// Forget the smoothed position, for when the camera is moved by hand or the target teleports
+    pub fn reset(&mut self) {
+        self.center = None;
+    }
+
+    /// Work out where the center of the view should be this frame, given the current view.
+    /// `CameraLayer::update_follow` calls this with the layer's own view.
+    pub fn update(&mut self, view: Rect, target: Vec2, velocity: Vec2, delta: f32) -> Vec2 {
+        let center = self.center.unwrap_or(view.center());
+        let focus = target + velocity * self.lookahead;
+
+        // Move just far enough to bring the focus back inside the deadzone
+        let deadzone = self.deadzone.offset(center);
+        let mut desired = center;
+        if focus.x < deadzone.left() {
+            desired.x += focus.x - deadzone.left();
+        }
+        else if focus.x > deadzone.right() {
+            desired.x += focus.x - deadzone.right();
+        }
+        if focus.y < deadzone.top() {
+            desired.y += focus.y - deadzone.top();
+        }
+        else if focus.y > deadzone.bottom() {
+            desired.y += focus.y - deadzone.bottom();
+        }
+
+        // Frame rate independent exponential ease towards the desired position
+        let mut center = if self.smoothing > 0.0 {
+            center + (desired - center) * (1.0 - (-self.smoothing * delta).exp())
+        }
+        else {
+            desired
+        };
+
+        if let Some(bounds) = self.bounds {
+            center.x = clamp_axis(center.x, view.w, bounds.left(), bounds.right());
+            center.y = clamp_axis(center.y, view.h, bounds.top(), bounds.bottom());
+        }
+
+        self.center = Some(center);
+
+        if self.pixel_snap {
+            center.round()
+        }
+        else {
+            center
+        }
+    }
+}
+
+/// Keep a view of `size` centered at `center` within `min..max`, centering it when it doesn't fit
+fn clamp_axis(center: f32, size: f32, min: f32, max: f32) -> f32 {
+    if max - min <= size {
+        (min + max) * 0.5
+    }
+    else {
+        center.clamp(min + size * 0.5, max - size * 0.5)
+    }
+}
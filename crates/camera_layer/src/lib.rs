@@ -1,7 +1,28 @@
+// The code generated by nanoserde's DeJson derive for optional fields trips this lint
+#![allow(clippy::question_mark)]
+
+pub mod follow;
+pub mod effects;
+pub mod stack;
+pub mod post_process;
+pub mod embed;
+pub mod presentation;
+
+use effects::CameraEffects;
+use embed::LayerQuad;
+use follow::CameraFollow;
+use post_process::PostProcess;
+use presentation::Presentation;
+use std::cell::Cell;
 use macroquad::{
     prelude::{
+        vec2,
+        vec3,
+        Camera,
         Camera2D,
         Rect,
+        UVec2,
+        Color,
         Vec2,
         WHITE
     },
@@ -18,8 +39,93 @@ use macroquad::{
     }
 };
 
+/// How a layer's render target is fitted to the area it's drawn into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScalingMode {
+    /// The largest whole multiple that fits, letterboxed, so every pixel stays the same size.
+    /// Falls back to fractional scaling when the area is smaller than the layer.
+    Integer,
+
+    /// The largest scale that fits, keeping the aspect ratio, letterboxed
+    #[default]
+    Fractional,
+
+    /// Fill the area exactly, distorting the aspect ratio
+    Stretch,
+
+    /// The smallest scale that covers the area, keeping the aspect ratio and cutting off the overflow
+    Crop,
+}
+
+/// What a layer does when the area it's drawn into changes size, see `CameraLayer::update_size`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ResizePolicy {
+    /// Keep the render target and view as they are and let the scaling mode fit them, the default
+    #[default]
+    Fixed,
+
+    /// Always show this much of the world, re-rendering at however many pixels it takes up on screen
+    /// so nothing gets blurry or blocky as the window grows
+    VirtualResolution(Vec2),
+
+    /// Show at least this much of the world at one render target pixel per world unit,
+    /// showing more along whichever side of the window is longer instead of adding bars
+    MinimumArea(Vec2),
+}
+
+impl ResizePolicy {
+    /// Render target resolution and area of the world to show when drawn into the target size,
+    /// `None` when the layer keeps its size. `render_scale` multiplies the resolution without changing the area.
+    pub fn get_layout(&self, target_size: Vec2, scaling_mode: ScalingMode, render_scale: f32) -> Option<(UVec2, Vec2)> {
+        let to_resolution = |size: Vec2| (size * render_scale).round().max(Vec2::ONE).as_uvec2();
+
+        match *self {
+            ResizePolicy::Fixed => None,
+            ResizePolicy::VirtualResolution(area) => {
+                let presentation = Presentation::fit(area, target_size, scaling_mode);
+                Some((to_resolution(presentation.get_rect().size()), area))
+            }
+            ResizePolicy::MinimumArea(area) => {
+                // Only whole pixel scaling changes how big a world unit is, the other modes would just show less than the area
+                let scaling_mode = if scaling_mode == ScalingMode::Integer { ScalingMode::Integer } else { ScalingMode::Fractional };
+                let scale = Presentation::fit(area, target_size, scaling_mode).get_scale().x;
+
+                // Whole pixel scaling can't cover a leftover odd pixel, so leave it empty rather than round the layer up
+                // past what the scale fits. Fractional scaling rounds up and is stretched over the rest, see `get_presentation_mode`.
+                let size = target_size / scale;
+                let size = if scaling_mode == ScalingMode::Integer { size.floor() } else { size.ceil() };
+
+                let resolution = to_resolution(size);
+                Some((resolution, resolution.as_vec2() / render_scale))
+            }
+        }
+    }
+
+    /// The scaling mode a layer is actually drawn with. A minimum area layer that isn't scaled by whole pixels
+    /// is within a pixel of the target's shape, so it's stretched to fill it rather than leave a sliver of a bar.
+    pub fn get_presentation_mode(&self, scaling_mode: ScalingMode) -> ScalingMode {
+        match self {
+            ResizePolicy::MinimumArea(_) if scaling_mode != ScalingMode::Integer => ScalingMode::Stretch,
+            _ => scaling_mode,
+        }
+    }
+}
+
 pub struct CameraLayer {
     pub camera: Camera2D,
+    scaling_mode: ScalingMode,
+    follow: Option<CameraFollow>,
+    effects: CameraEffects,
+    post_process: Option<PostProcess>,
+
+    // Where the layer was last drawn, so input can be mapped through what's actually on screen
+    presentation: Cell<Option<Presentation>>,
+
+    resize_policy: ResizePolicy,
+    render_scale: f32,
+
+    // Size of the area the layer was last fitted to, so the render target is only rebuilt when it changes
+    fitted_size: Option<Vec2>,
 }
 
 impl CameraLayer {
@@ -39,7 +145,72 @@ impl CameraLayer {
         // Flip Vertically? Apparently fixes a bug
         camera.zoom.y = -camera.zoom.y;
 
-        CameraLayer { camera }
+        CameraLayer {
+            camera,
+            scaling_mode: ScalingMode::default(),
+            follow: None,
+            effects: CameraEffects::default(),
+            post_process: None,
+            presentation: Cell::new(None),
+            resize_policy: ResizePolicy::default(),
+            render_scale: 1.0,
+            fitted_size: None,
+        }
+    }
+
+    pub fn set_resize_policy(&mut self, resize_policy: ResizePolicy) {
+        self.resize_policy = resize_policy;
+        self.fitted_size = None;
+    }
+
+    /// Render target pixels per pixel the layer covers on screen for layers that resize, e.g. 0.5 for a faster, blockier picture
+    pub fn set_render_scale(&mut self, render_scale: f32) {
+        self.render_scale = render_scale.max(f32::EPSILON);
+        self.fitted_size = None;
+    }
+
+    /// Switch the render target to a new resolution while showing the same area of the world,
+    /// e.g. from a graphics setting. Layers that resize with the window choose their own resolution, see `set_render_scale`.
+    pub fn set_resolution(&mut self, width: u32, height: u32) {
+        let view_size = self.get_view_rect().size();
+        self.rebuild(UVec2::new(width, height), view_size);
+    }
+
+    /// Fit the layer to the size it's about to be drawn into according to its resize policy, usually once a frame.
+    /// Does nothing when the size hasn't changed.
+    pub fn update_size(&mut self, target_width: f32, target_height: f32) {
+        let target_size = vec2(target_width, target_height);
+        if self.fitted_size == Some(target_size) {
+            return;
+        }
+        self.fitted_size = Some(target_size);
+
+        if let Some((resolution, view_size)) = self.resize_policy.get_layout(target_size, self.scaling_mode, self.render_scale) {
+            self.rebuild(resolution, view_size);
+        }
+    }
+
+    /// Recreate the render target if its resolution changed and zoom to show `view_size` of the world,
+    /// keeping the middle of the view where it was
+    fn rebuild(&mut self, resolution: UVec2, view_size: Vec2) {
+        let center = self.get_view_rect().center();
+
+        if resolution != UVec2::new(self.get_width() as u32, self.get_height() as u32) {
+            let render_target = render_target(resolution.x, resolution.y);
+            render_target.texture.set_filter(FilterMode::Nearest);
+
+            if let Some(previous) = self.camera.render_target.replace(render_target) {
+                previous.delete();
+            }
+            if let Some(post_process) = self.post_process.as_mut() {
+                post_process.prepare(resolution.x as f32, resolution.y as f32);
+            }
+            self.presentation.set(None);
+        }
+
+        // Keep whichever way the camera is flipped
+        self.camera.zoom = Vec2::new((2.0 / view_size.x).copysign(self.camera.zoom.x), (2.0 / view_size.y).copysign(self.camera.zoom.y));
+        self.camera.target += center - self.get_view_rect().center();
     }
 
     pub fn translate(&mut self, x: f32, y: f32) {
@@ -54,32 +225,152 @@ impl CameraLayer {
         self.camera.target = pos;
     }
 
+    pub fn set_scaling_mode(&mut self, scaling_mode: ScalingMode) {
+        self.scaling_mode = scaling_mode;
+        self.fitted_size = None;
+    }
+
+    /// Move the camera so the middle of the view is on a point
+    pub fn center_on(&mut self, point: Vec2) {
+        self.camera.target += point - self.get_view_rect().center();
+
+        if let Some(follow) = self.follow.as_mut() {
+            follow.reset();
+        }
+    }
+
+    /// Hand the camera over to a follow controller, driven by `update_follow`
+    pub fn set_follow(&mut self, follow: Option<CameraFollow>) {
+        self.follow = follow;
+    }
+
+    pub fn get_follow_mut(&mut self) -> Option<&mut CameraFollow> {
+        self.follow.as_mut()
+    }
+
+    /// Move the camera after a target, usually once a frame after the target has moved.
+    /// `velocity` is in world units per second and `delta` is the frame time in seconds.
+    pub fn update_follow(&mut self, target: Vec2, velocity: Vec2, delta: f32) {
+        let view = self.get_view_rect();
+        if let Some(follow) = self.follow.as_mut() {
+            // The camera's target isn't necessarily the middle of the view, so move it by however far the middle moves
+            let center = follow.update(view, target, velocity, delta);
+            self.camera.target += center - view.center();
+        }
+    }
+
+    pub fn get_effects(&self) -> &CameraEffects {
+        &self.effects
+    }
+
+    /// Shake, zoom and rotate the view without moving `camera`, which stays wherever it was put or followed to
+    pub fn get_effects_mut(&mut self) -> &mut CameraEffects {
+        &mut self.effects
+    }
+
+    pub fn update_effects(&mut self, delta: f32) {
+        self.effects.update(delta);
+    }
+
+    /// The camera with effects applied, this is the one to draw with
+    pub fn get_camera(&self) -> Camera2D {
+        self.effects.apply(&self.camera, self.get_view_rect())
+    }
+
+    /// Run the render target through a chain of shaders whenever the layer is drawn
+    pub fn set_post_process(&mut self, post_process: Option<PostProcess>) {
+        self.post_process = post_process;
+
+        let (width, height) = (self.get_width(), self.get_height());
+        if let Some(post_process) = self.post_process.as_mut() {
+            post_process.prepare(width, height);
+        }
+    }
+
+    pub fn get_post_process_mut(&mut self) -> Option<&mut PostProcess> {
+        self.post_process.as_mut()
+    }
+
+    /// The layer's texture once it's been through the post processing chain, if it has one
+    pub fn get_output_texture(&self) -> Texture2D {
+        match self.post_process.as_ref() {
+            Some(post_process) => post_process.apply(*self.get_texture()),
+            None => *self.get_texture(),
+        }
+    }
+
     pub fn draw(&self) {
         self.draw_ex(screen_width(), screen_height());
     }
 
     pub fn draw_ex(&self, target_width: f32, target_height: f32) {
+        self.draw_ex_with_color(target_width, target_height, WHITE);
+    }
+
+    /// Draw tinted by `color`, whose alpha fades the whole layer
+    pub fn draw_ex_with_color(&self, target_width: f32, target_height: f32, color: Color) {
+        self.draw_output(self.get_output_texture(), target_width, target_height, color);
+    }
 
-        let (left_padding, top_padding, dimensions) = self.get_size_and_padding(target_width, target_height);
+    /// Draw a texture the size of the layer where the layer would be drawn
+    pub(crate) fn draw_output(&self, texture: Texture2D, target_width: f32, target_height: f32, color: Color) {
+
+        let presentation = self.get_presentation_for(target_width, target_height);
+        self.presentation.set(Some(presentation));
+        let rect = presentation.get_rect();
 
         draw_texture_ex(
-            *self.get_texture(),
-            left_padding,
-            top_padding,
-            WHITE,
+            texture,
+            rect.x,
+            rect.y,
+            color,
             DrawTextureParams {
-                dest_size: Some(dimensions),
+                dest_size: Some(rect.size()),
                 ..Default::default()
             }
         )
     }
 
+    /// Draw the layer into another layer, with that layer's camera set
+    pub fn draw_quad(&self, quad: &LayerQuad, color: Color) {
+        quad.draw_texture(self.get_output_texture(), color);
+    }
+
+    /// Where the layer was last drawn, or where `draw` would put it if it hasn't been drawn yet
+    pub fn get_presentation(&self) -> Presentation {
+        self.presentation.get().unwrap_or_else(|| self.get_presentation_for(screen_width(), screen_height()))
+    }
+
+    /// Where the layer would be drawn into the target size
+    pub fn get_presentation_for(&self, target_width: f32, target_height: f32) -> Presentation {
+        Presentation::fit(vec2(self.get_width(), self.get_height()), vec2(target_width, target_height), self.resize_policy.get_presentation_mode(self.scaling_mode))
+    }
+
+    /// A pixel of the render target, from (0, 0) at its top left, to a point in the world
+    pub fn layer_to_world(&self, point: Vec2) -> Vec2 {
+        self.get_presentation().layer_to_world(&self.get_camera(), point)
+    }
+
+    /// A point in the world to where it's drawn on the render target, in pixels from its top left
+    pub fn world_to_layer(&self, point: Vec2) -> Vec2 {
+        self.get_presentation().world_to_layer(&self.get_camera(), point)
+    }
+
+    /// A window pixel to a render target pixel, taking the bars and scaling around the layer into account
+    pub fn screen_to_layer(&self, point: Vec2) -> Vec2 {
+        self.get_presentation().window_to_layer(point)
+    }
+
+    pub fn layer_to_screen(&self, point: Vec2) -> Vec2 {
+        self.get_presentation().layer_to_window(point)
+    }
+
     pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
-        self.camera.screen_to_world(point)
+        self.get_presentation().window_to_world(&self.get_camera(), point)
     }
 
     pub fn world_to_screen(&self, point: Vec2) -> Vec2 {
-        self.camera.world_to_screen(point)
+        self.get_presentation().world_to_window(&self.get_camera(), point)
     }
 
     #[inline]
@@ -102,19 +393,46 @@ impl CameraLayer {
         self.camera.target
     }
 
-    pub fn get_size(&self, target_width: f32, target_height: f32) -> Vec2 {
-        let scale_factor = self.get_min_scale_factor(target_width, target_height);
+    #[inline]
+    pub fn get_scaling_mode(&self) -> ScalingMode {
+        self.scaling_mode
+    }
 
-        Vec2::new(self.get_width() * scale_factor, self.get_height() * scale_factor)
+    #[inline]
+    pub fn get_resize_policy(&self) -> ResizePolicy {
+        self.resize_policy
     }
 
-    pub fn get_size_and_padding(&self, target_width: f32, target_height: f32) -> (f32, f32, Vec2) {
-        let size = self.get_size(target_width, target_height);
+    #[inline]
+    pub fn get_render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// The area of the world the layer shows before effects, from the camera rather than the screen
+    pub fn get_view_rect(&self) -> Rect {
+        let inverse = self.camera.matrix().inverse();
+        let first = inverse.transform_point3(vec3(-1.0, -1.0, 0.0));
+        let second = inverse.transform_point3(vec3(1.0, 1.0, 0.0));
+
+        let min = first.min(second);
+        let max = first.max(second);
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
 
-        let left_padding = (target_width - size.x) / 2.0;
-        let top_padding = (target_height - size.y) / 2.0;
+    /// Scale on each axis when drawn into the target size with the layer's scaling mode
+    pub fn get_scale(&self, target_width: f32, target_height: f32) -> Vec2 {
+        self.get_presentation_for(target_width, target_height).get_scale()
+    }
+
+    pub fn get_size(&self, target_width: f32, target_height: f32) -> Vec2 {
+        self.get_presentation_for(target_width, target_height).get_rect().size()
+    }
+
+    /// Padding is negative on the axis being cut off when cropping
+    pub fn get_size_and_padding(&self, target_width: f32, target_height: f32) -> (f32, f32, Vec2) {
+        let rect = self.get_presentation_for(target_width, target_height).get_rect();
 
-        (left_padding, top_padding, size)
+        (rect.x, rect.y, rect.size())
     }
 
     pub fn get_scale_factor(&self, target_width: f32, target_height: f32) -> (f32, f32) {
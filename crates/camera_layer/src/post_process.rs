@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+
+use macroquad::{
+    material::{gl_use_default_material, gl_use_material, load_material, Material, MaterialParams},
+    miniquad::UniformType,
+    prelude::{load_string, pop_camera_state, push_camera_state, set_camera, vec2, Camera2D, Rect, WHITE},
+    texture::{draw_texture_ex, render_target, DrawTextureParams, FilterMode, RenderTarget, Texture2D},
+};
+use nanoserde::DeJson;
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying vec2 uv;
+varying vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+"#;
+
+const FRAGMENT_HEADER: &str = r#"#version 100
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+varying vec2 uv;
+varying vec4 color;
+
+uniform sampler2D Texture;
+uniform vec2 Resolution;
+"#;
+
+const SCANLINES_SHADER: &str = r#"
+uniform float Intensity;
+uniform float Thickness;
+
+void main() {
+    vec4 source = texture2D(Texture, uv);
+
+    // Every other band of whole pixel rows, so lines never fall between pixels and beat against them
+    float line = mod(floor(uv.y * Resolution.y / max(Thickness, 1.0)), 2.0);
+    gl_FragColor = vec4(source.rgb * (1.0 - Intensity * line), source.a);
+}
+"#;
+
+const BARREL_SHADER: &str = r#"
+uniform float Curvature;
+
+void main() {
+    vec2 centered = uv * 2.0 - 1.0;
+    centered *= 1.0 + Curvature * dot(centered, centered);
+    vec2 curved = centered * 0.5 + 0.5;
+
+    // Past the curved edge of the glass
+    if (curved.x < 0.0 || curved.x > 1.0 || curved.y < 0.0 || curved.y > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+    }
+    else {
+        gl_FragColor = texture2D(Texture, curved);
+    }
+}
+"#;
+
+const BLOOM_SHADER: &str = r#"
+uniform float Threshold;
+uniform float Intensity;
+uniform float Radius;
+
+void main() {
+    vec4 source = texture2D(Texture, uv);
+
+    // Blur whatever is brighter than the threshold and add it back on top
+    vec3 glow = vec3(0.0);
+    float total = 0.0;
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            vec2 offset = vec2(float(x), float(y));
+            float weight = exp(-dot(offset, offset) * 0.25);
+            vec3 tap = texture2D(Texture, uv + offset * Radius / Resolution).rgb;
+            glow += max(tap - vec3(Threshold), vec3(0.0)) * weight;
+            total += weight;
+        }
+    }
+
+    gl_FragColor = vec4(source.rgb + glow / total * Intensity, source.a);
+}
+"#;
+
+const CHROMATIC_ABERRATION_SHADER: &str = r#"
+uniform float Offset;
+
+void main() {
+    // Red and blue drift apart towards the edges, green stays put
+    vec2 direction = (uv - 0.5) * 2.0 * Offset / Resolution;
+    vec4 source = texture2D(Texture, uv);
+    float red = texture2D(Texture, uv + direction).r;
+    float blue = texture2D(Texture, uv - direction).b;
+    gl_FragColor = vec4(red, source.g, blue, source.a);
+}
+"#;
+
+const VIGNETTE_SHADER: &str = r#"
+uniform float Intensity;
+uniform float Softness;
+
+void main() {
+    vec4 source = texture2D(Texture, uv);
+
+    // 0 in the middle and 1 in the corners
+    float edge = length(uv - 0.5) * 1.4142136;
+    float shade = smoothstep(1.0 - Softness, 1.0, edge);
+    gl_FragColor = vec4(source.rgb * (1.0 - Intensity * shade), source.a);
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostEffect {
+    /// Darkens alternate lines like the gaps between a CRT's rows
+    Scanlines,
+
+    /// Bulges the picture out like curved glass
+    Barrel,
+
+    /// Bright areas glow onto their surroundings
+    Bloom,
+
+    /// Red and blue separate towards the edges
+    ChromaticAberration,
+
+    /// Darkens the corners
+    Vignette,
+}
+
+impl PostEffect {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "scanlines" => Some(PostEffect::Scanlines),
+            "barrel" => Some(PostEffect::Barrel),
+            "bloom" => Some(PostEffect::Bloom),
+            "chromatic_aberration" => Some(PostEffect::ChromaticAberration),
+            "vignette" => Some(PostEffect::Vignette),
+            _ => None,
+        }
+    }
+
+    fn get_shader(&self) -> &'static str {
+        match self {
+            PostEffect::Scanlines => SCANLINES_SHADER,
+            PostEffect::Barrel => BARREL_SHADER,
+            PostEffect::Bloom => BLOOM_SHADER,
+            PostEffect::ChromaticAberration => CHROMATIC_ABERRATION_SHADER,
+            PostEffect::Vignette => VIGNETTE_SHADER,
+        }
+    }
+
+    /// Name, uniform and default of each parameter the effect takes.
+    /// Offsets, radii and scanline thickness are in pixels of the render target.
+    fn get_params(&self) -> &'static [(&'static str, &'static str, f32)] {
+        match self {
+            PostEffect::Scanlines => &[("intensity", "Intensity", 0.25), ("thickness", "Thickness", 1.0)],
+            PostEffect::Barrel => &[("curvature", "Curvature", 0.08)],
+            PostEffect::Bloom => &[("threshold", "Threshold", 0.6), ("intensity", "Intensity", 0.6), ("radius", "Radius", 2.0)],
+            PostEffect::ChromaticAberration => &[("offset", "Offset", 1.0)],
+            PostEffect::Vignette => &[("intensity", "Intensity", 0.5), ("softness", "Softness", 0.6)],
+        }
+    }
+}
+
+#[derive(DeJson)]
+struct PassConfig {
+    effect: String,
+    enabled: Option<bool>,
+    params: Option<HashMap<String, f32>>,
+}
+
+#[derive(DeJson)]
+struct PostProcessConfig {
+    passes: Vec<PassConfig>,
+}
+
+struct PostPass {
+    effect: PostEffect,
+    material: Material,
+    enabled: bool,
+    params: Vec<f32>,
+}
+
+/// A chain of full screen shader passes a `CameraLayer`'s render target goes through before it's drawn
+pub struct PostProcess {
+    passes: Vec<PostPass>,
+
+    // Passes take turns reading from one and drawing into the other
+    targets: Vec<RenderTarget>,
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PostProcess {
+    pub fn new() -> Self {
+        PostProcess {
+            passes: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Parse a chain from JSON, passes run in the order they're listed and parameters left out keep their defaults:
+    ///
+    /// ```json
+    /// { "passes": [{ "effect": "scanlines", "params": { "intensity": 0.3 } }, { "effect": "vignette", "enabled": false }] }
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config = PostProcessConfig::deserialize_json(json).map_err(|err| format!("Unable to parse post processing config: {}", err))?;
+
+        let mut post_process = PostProcess::new();
+
+        for pass in config.passes {
+            let effect = PostEffect::from_name(&pass.effect).ok_or_else(|| format!("Unknown post processing effect {}", pass.effect))?;
+            let index = post_process.add_pass(effect)?;
+
+            post_process.set_enabled(index, pass.enabled.unwrap_or(true));
+            for (name, value) in pass.params.unwrap_or_default() {
+                post_process.set_param(index, &name, value)?;
+            }
+        }
+
+        Ok(post_process)
+    }
+
+    pub async fn from_file(path: &str) -> Result<Self, String> {
+        let contents = load_string(path).await.map_err(|_| format!("Unable to load post processing config {}", path))?;
+        Self::from_json(&contents).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    /// Add a pass to the end of the chain with default parameters, returning its index
+    pub fn add_pass(&mut self, effect: PostEffect) -> Result<usize, String> {
+        let shader = format!("{}{}", FRAGMENT_HEADER, effect.get_shader());
+        let mut uniforms = vec![("Resolution".to_owned(), UniformType::Float2)];
+        uniforms.extend(effect.get_params().iter().map(|(_, uniform, _)| (uniform.to_string(), UniformType::Float1)));
+
+        let material = load_material(VERTEX_SHADER, &shader, MaterialParams {
+            uniforms,
+            ..Default::default()
+        }).map_err(|err| format!("Unable to build the {:?} shader: {}", effect, err))?;
+
+        self.passes.push(PostPass {
+            effect,
+            material,
+            enabled: true,
+            params: effect.get_params().iter().map(|(_, _, default)| *default).collect(),
+        });
+
+        Ok(self.passes.len() - 1)
+    }
+
+    pub fn get_pass_count(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn get_effect(&self, index: usize) -> PostEffect {
+        self.passes[index].effect
+    }
+
+    /// Index of the first pass running an effect
+    pub fn find_pass(&self, effect: PostEffect) -> Option<usize> {
+        self.passes.iter().position(|pass| pass.effect == effect)
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.passes[index].enabled = enabled;
+    }
+
+    pub fn is_enabled(&self, index: usize) -> bool {
+        self.passes[index].enabled
+    }
+
+    pub fn set_param(&mut self, index: usize, name: &str, value: f32) -> Result<(), String> {
+        let pass = &mut self.passes[index];
+        let param = pass.effect.get_params().iter().position(|(param, _, _)| *param == name)
+            .ok_or_else(|| format!("{:?} has no parameter {}", pass.effect, name))?;
+
+        pass.params[param] = value;
+        Ok(())
+    }
+
+    pub fn get_param(&self, index: usize, name: &str) -> Option<f32> {
+        let pass = &self.passes[index];
+        let param = pass.effect.get_params().iter().position(|(param, _, _)| *param == name)?;
+
+        Some(pass.params[param])
+    }
+
+    /// Create the render targets the passes draw into, for a layer of this size
+    pub(crate) fn prepare(&mut self, width: f32, height: f32) {
+        for target in self.targets.drain(..) {
+            target.delete();
+        }
+        self.targets = (0..2).map(|_| {
+            let target = render_target(width as u32, height as u32);
+            target.texture.set_filter(FilterMode::Nearest);
+            target
+        }).collect();
+    }
+
+    /// Run every enabled pass over `texture`, returning the texture holding the result
+    pub(crate) fn apply(&self, texture: Texture2D) -> Texture2D {
+        if self.targets.is_empty() || !self.passes.iter().any(|pass| pass.enabled) {
+            return texture;
+        }
+
+        let size = vec2(self.targets[0].texture.width(), self.targets[0].texture.height());
+        push_camera_state();
+
+        let mut source = texture;
+        for (index, pass) in self.passes.iter().filter(|pass| pass.enabled).enumerate() {
+            let target = self.targets[index % 2];
+
+            // Flipped the same way as a layer's camera, so the result comes out the same way up as the layer
+            let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, size.x, size.y));
+            camera.zoom.y = -camera.zoom.y;
+            camera.render_target = Some(target);
+            set_camera(&camera);
+
+            pass.material.set_uniform("Resolution", size);
+            for ((_, uniform, _), value) in pass.effect.get_params().iter().zip(pass.params.iter()) {
+                pass.material.set_uniform(uniform, *value);
+            }
+
+            gl_use_material(pass.material);
+            draw_texture_ex(source, 0.0, 0.0, WHITE, DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            });
+
+            source = target.texture;
+        }
+
+        gl_use_default_material();
+        pop_camera_state();
+
+        source
+    }
+}
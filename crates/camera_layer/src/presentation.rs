@@ -0,0 +1,92 @@
+use macroquad::prelude::{vec2, Camera, Camera2D, Rect, Vec2};
+
+use crate::ScalingMode;
+
+/// Where a layer's render target ends up on the screen, for converting between window pixels,
+/// render target pixels and the layer's world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Presentation {
+    layer_size: Vec2,
+
+    // In window pixels, reaching past the window on the axis being cut off when cropping
+    rect: Rect,
+}
+
+impl Presentation {
+    pub fn new(layer_size: Vec2, rect: Rect) -> Self {
+        Presentation { layer_size, rect }
+    }
+
+    /// Fit a layer into an area of the window with a scaling mode, centered
+    pub fn fit(layer_size: Vec2, target_size: Vec2, scaling_mode: ScalingMode) -> Self {
+        let scale_factor = target_size / layer_size;
+        let min_scale = scale_factor.min_element();
+
+        let scale = match scaling_mode {
+            ScalingMode::Integer if min_scale >= 1.0 => Vec2::splat(min_scale.floor()),
+            ScalingMode::Integer | ScalingMode::Fractional => Vec2::splat(min_scale),
+            ScalingMode::Stretch => scale_factor,
+            ScalingMode::Crop => Vec2::splat(scale_factor.max_element()),
+        };
+
+        let size = layer_size * scale;
+        let mut padding = (target_size - size) / 2.0;
+
+        // Keep whole pixel scaling lined up with the screen's pixels too
+        if scaling_mode == ScalingMode::Integer {
+            padding = padding.floor();
+        }
+
+        Presentation::new(layer_size, Rect::new(padding.x, padding.y, size.x, size.y))
+    }
+
+    pub fn get_layer_size(&self) -> Vec2 {
+        self.layer_size
+    }
+
+    pub fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Window pixels per render target pixel on each axis
+    pub fn get_scale(&self) -> Vec2 {
+        self.rect.size() / self.layer_size
+    }
+
+    /// Whether a window pixel shows part of the layer rather than the bars around it
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.rect.contains(point)
+    }
+
+    /// A window pixel to a render target pixel, both from the top left
+    pub fn window_to_layer(&self, point: Vec2) -> Vec2 {
+        (point - self.rect.point()) / self.get_scale()
+    }
+
+    pub fn layer_to_window(&self, point: Vec2) -> Vec2 {
+        point * self.get_scale() + self.rect.point()
+    }
+
+    /// A render target pixel to a point in the world seen through `camera`
+    pub fn layer_to_world(&self, camera: &Camera2D, point: Vec2) -> Vec2 {
+        // The layer's camera is flipped so its render target comes out the right way up, putting the top at -1
+        let ndc = point / self.layer_size * 2.0 - Vec2::ONE;
+        let world = camera.matrix().inverse().transform_point3(ndc.extend(0.0));
+
+        vec2(world.x, world.y)
+    }
+
+    pub fn world_to_layer(&self, camera: &Camera2D, point: Vec2) -> Vec2 {
+        let ndc = camera.matrix().transform_point3(point.extend(0.0));
+
+        (vec2(ndc.x, ndc.y) + Vec2::ONE) * 0.5 * self.layer_size
+    }
+
+    pub fn window_to_world(&self, camera: &Camera2D, point: Vec2) -> Vec2 {
+        self.layer_to_world(camera, self.window_to_layer(point))
+    }
+
+    pub fn world_to_window(&self, camera: &Camera2D, point: Vec2) -> Vec2 {
+        self.layer_to_window(self.world_to_layer(camera, point))
+    }
+}
@@ -0,0 +1,367 @@
+use std::cmp::Reverse;
+
+use macroquad::{
+    material::{gl_use_default_material, gl_use_material, load_material, Material, MaterialParams},
+    miniquad::{BlendFactor, BlendState, BlendValue, Equation, PipelineParams},
+    prelude::{clear_background, set_camera, set_default_camera, vec2, Color, Vec2, WHITE},
+    texture::Texture2D,
+    window::{screen_height, screen_width},
+};
+
+use crate::{embed::LayerQuad, CameraLayer};
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+"#;
+
+const ADDITIVE_FRAGMENT_SHADER: &str = r#"#version 100
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = color * texture2D(Texture, uv);
+}
+"#;
+
+// Multiplying by white leaves the layer below alone, so transparency and opacity fade towards white
+const MULTIPLY_FRAGMENT_SHADER: &str = r#"#version 100
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+    lowp vec4 source = color * texture2D(Texture, uv);
+    gl_FragColor = vec4(mix(vec3(1.0), source.rgb, source.a), 1.0);
+}
+"#;
+
+/// How a layer is combined with the layers below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Drawn over the top, letting the layers below show through transparent pixels
+    #[default]
+    Alpha,
+
+    /// Brightens the layers below, for glows and light
+    Additive,
+
+    /// Darkens the layers below, for shadows and tinting
+    Multiply,
+}
+
+/// Refers to a layer added to a `LayerStack`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerId(usize);
+
+struct StackLayer {
+    layer: CameraLayer,
+    z_order: i32,
+    blend_mode: BlendMode,
+    opacity: f32,
+    visible: bool,
+    clear_color: Option<Color>,
+
+    // How far the layer moves for each unit the master camera moves, and where it would be with the master at zero
+    parallax: Option<(Vec2, Vec2)>,
+
+    // The layer it's shown inside of, and where
+    embed: Option<(LayerId, LayerQuad)>,
+}
+
+impl StackLayer {
+    fn get_color(&self) -> Color {
+        Color::new(WHITE.r, WHITE.g, WHITE.b, self.opacity)
+    }
+}
+
+/// A set of `CameraLayer`s rendered and composited onto the screen together, lowest `z_order` first
+pub struct LayerStack {
+    layers: Vec<StackLayer>,
+    master: Option<LayerId>,
+    additive_material: Material,
+    multiply_material: Material,
+}
+
+impl Default for LayerStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayerStack {
+    pub fn new() -> Self {
+        let blend_material = |fragment_shader, blend_state| {
+            load_material(VERTEX_SHADER, fragment_shader, MaterialParams {
+                pipeline_params: PipelineParams {
+                    color_blend: Some(blend_state),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }).expect("Layer blend shaders should always compile")
+        };
+
+        LayerStack {
+            layers: Vec::new(),
+            master: None,
+            additive_material: blend_material(ADDITIVE_FRAGMENT_SHADER, BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::One,
+            )),
+            multiply_material: blend_material(MULTIPLY_FRAGMENT_SHADER, BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::DestinationColor),
+                BlendFactor::Zero,
+            )),
+        }
+    }
+
+    /// Add a layer drawn above every layer with a lower `z_order`, and above earlier layers with the same one
+    pub fn add(&mut self, layer: CameraLayer, z_order: i32) -> LayerId {
+        self.layers.push(StackLayer {
+            layer,
+            z_order,
+            blend_mode: BlendMode::default(),
+            opacity: 1.0,
+            visible: true,
+            clear_color: Some(Color::new(0.0, 0.0, 0.0, 0.0)),
+            parallax: None,
+            embed: None,
+        });
+
+        LayerId(self.layers.len() - 1)
+    }
+
+    pub fn get_layer(&self, id: LayerId) -> &CameraLayer {
+        &self.layers[id.0].layer
+    }
+
+    pub fn get_layer_mut(&mut self, id: LayerId) -> &mut CameraLayer {
+        &mut self.layers[id.0].layer
+    }
+
+    pub fn set_z_order(&mut self, id: LayerId, z_order: i32) {
+        self.layers[id.0].z_order = z_order;
+    }
+
+    pub fn set_blend_mode(&mut self, id: LayerId, blend_mode: BlendMode) {
+        self.layers[id.0].blend_mode = blend_mode;
+    }
+
+    /// How much of the layer shows, from 0 for none to 1 for all of it
+    pub fn set_opacity(&mut self, id: LayerId, opacity: f32) {
+        self.layers[id.0].opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// Hidden layers are neither rendered nor composited
+    pub fn set_visible(&mut self, id: LayerId, visible: bool) {
+        self.layers[id.0].visible = visible;
+    }
+
+    /// Color the layer's render target is cleared to before it's rendered, transparent by default.
+    /// `None` keeps last frame's contents.
+    pub fn set_clear_color(&mut self, id: LayerId, clear_color: Option<Color>) {
+        self.layers[id.0].clear_color = clear_color;
+    }
+
+    pub fn get_z_order(&self, id: LayerId) -> i32 {
+        self.layers[id.0].z_order
+    }
+
+    pub fn get_blend_mode(&self, id: LayerId) -> BlendMode {
+        self.layers[id.0].blend_mode
+    }
+
+    pub fn get_opacity(&self, id: LayerId) -> f32 {
+        self.layers[id.0].opacity
+    }
+
+    pub fn is_visible(&self, id: LayerId) -> bool {
+        self.layers[id.0].visible
+    }
+
+    /// The layer other layers' parallax is measured against, usually the one following the player
+    pub fn set_master(&mut self, master: Option<LayerId>) {
+        self.master = master;
+        for index in 0..self.layers.len() {
+            if let Some((factor, _)) = self.layers[index].parallax {
+                self.set_parallax(LayerId(index), Some(factor));
+            }
+        }
+    }
+
+    pub fn get_master(&self) -> Option<LayerId> {
+        self.master
+    }
+
+    /// Move the layer by `factor` times however far the master camera moves, from where both are now.
+    /// 1 moves with the master, 0.5 lags behind like a distant background and 0 stays put.
+    /// `None` leaves the layer's camera alone.
+    pub fn set_parallax(&mut self, id: LayerId, factor: Option<Vec2>) {
+        let master = self.get_master_pos();
+        let layer = &mut self.layers[id.0];
+
+        layer.parallax = factor.map(|factor| (factor, layer.layer.get_pos() - master * factor));
+    }
+
+    fn get_master_pos(&self) -> Vec2 {
+        self.master.map_or(Vec2::ZERO, |master| self.get_layer(master).get_pos())
+    }
+
+    /// Move parallax layers to match the master camera, this happens as part of `draw`
+    pub fn update_parallax(&mut self) {
+        let master = self.get_master_pos();
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            if Some(LayerId(index)) == self.master {
+                continue;
+            }
+            if let Some((factor, origin)) = layer.parallax {
+                layer.layer.set_pos(origin + master * factor);
+            }
+        }
+    }
+
+    /// Show a layer inside another instead of on the screen, drawn over the host's contents when its `z_order`
+    /// is the same or higher and under them otherwise. `None` puts it back on the screen.
+    pub fn set_embed(&mut self, id: LayerId, embed: Option<(LayerId, LayerQuad)>) {
+        let mut host = embed.map(|(host, _)| host);
+        while let Some(ancestor) = host {
+            assert!(ancestor != id, "A layer can't be embedded in itself");
+            host = self.layers[ancestor.0].embed.map(|(host, _)| host);
+        }
+
+        self.layers[id.0].embed = embed;
+    }
+
+    pub fn get_embed(&self, id: LayerId) -> Option<(LayerId, LayerQuad)> {
+        self.layers[id.0].embed
+    }
+
+    /// Follow a point on the screen through every layer `id` is embedded in to a point in its world.
+    /// `None` when the point misses the layer.
+    pub fn screen_to_world(&self, id: LayerId, point: Vec2) -> Option<Vec2> {
+        let layer = &self.layers[id.0].layer;
+        match self.layers[id.0].embed {
+            Some((host, quad)) => {
+                let uv = quad.to_uv(self.screen_to_world(host, point)?)?;
+                Some(layer.layer_to_world(uv * vec2(layer.get_width(), layer.get_height())))
+            }
+            None => layer.get_presentation().contains(point).then(|| layer.screen_to_world(point)),
+        }
+    }
+
+    pub fn world_to_screen(&self, id: LayerId, point: Vec2) -> Vec2 {
+        let layer = &self.layers[id.0].layer;
+        match self.layers[id.0].embed {
+            Some((host, quad)) => {
+                let uv = layer.world_to_layer(point) / vec2(layer.get_width(), layer.get_height());
+                self.world_to_screen(host, quad.to_host(uv))
+            }
+            None => layer.world_to_screen(point),
+        }
+    }
+
+    fn get_embed_depth(&self, index: usize) -> usize {
+        let mut depth = 0;
+        let mut host = self.layers[index].embed;
+        while let Some((id, _)) = host {
+            depth += 1;
+            host = self.layers[id.0].embed;
+        }
+        depth
+    }
+
+    pub fn draw(&mut self, draw_layer: impl FnMut(LayerId, &CameraLayer)) {
+        self.draw_ex(screen_width(), screen_height(), draw_layer);
+    }
+
+    /// Render every visible layer, calling `draw_layer` with each layer's camera set,
+    /// then composite them onto the current camera to fill the target size
+    pub fn draw_ex(&mut self, target_width: f32, target_height: f32, mut draw_layer: impl FnMut(LayerId, &CameraLayer)) {
+        // Only layers drawn straight onto the target follow its size, embedded layers keep theirs
+        for layer in self.layers.iter_mut().filter(|layer| layer.embed.is_none()) {
+            layer.layer.update_size(target_width, target_height);
+        }
+        self.update_parallax();
+
+        // Embedded layers have to be rendered before the layers they're shown in, so go deepest first
+        let mut order: Vec<usize> = (0..self.layers.len()).filter(|index| self.layers[*index].visible).collect();
+        order.sort_by_key(|index| (Reverse(self.get_embed_depth(*index)), self.layers[*index].z_order));
+
+        for index in order.iter().copied() {
+            self.render_layer(index, &mut draw_layer);
+        }
+
+        set_default_camera();
+
+        // Post processing switches material, so get it out of the way before setting up blending
+        order.retain(|index| self.layers[*index].embed.is_none());
+        let outputs: Vec<Texture2D> = order.iter().map(|index| self.layers[*index].layer.get_output_texture()).collect();
+
+        for (index, output) in order.into_iter().zip(outputs) {
+            let layer = &self.layers[index];
+
+            self.use_blend_mode(layer.blend_mode);
+            layer.layer.draw_output(output, target_width, target_height, layer.get_color());
+        }
+
+        gl_use_default_material();
+    }
+
+    fn render_layer(&self, index: usize, draw_layer: &mut impl FnMut(LayerId, &CameraLayer)) {
+        let layer = &self.layers[index];
+
+        let mut embedded: Vec<usize> = (0..self.layers.len())
+            .filter(|child| self.layers[*child].visible && self.layers[*child].embed.map(|(host, _)| host) == Some(LayerId(index)))
+            .collect();
+        embedded.sort_by_key(|child| self.layers[*child].z_order);
+        let (below, above): (Vec<usize>, Vec<usize>) = embedded.into_iter().partition(|child| self.layers[*child].z_order < layer.z_order);
+
+        set_camera(&layer.layer.get_camera());
+        if let Some(clear_color) = layer.clear_color {
+            clear_background(clear_color);
+        }
+
+        self.draw_embedded(&below);
+        draw_layer(LayerId(index), &layer.layer);
+        self.draw_embedded(&above);
+    }
+
+    fn draw_embedded(&self, children: &[usize]) {
+        for child in children.iter().copied() {
+            let child = &self.layers[child];
+            if let Some((_, quad)) = child.embed {
+                let output = child.layer.get_output_texture();
+
+                self.use_blend_mode(child.blend_mode);
+                quad.draw_texture(output, child.get_color());
+                gl_use_default_material();
+            }
+        }
+    }
+
+    fn use_blend_mode(&self, blend_mode: BlendMode) {
+        match blend_mode {
+            BlendMode::Alpha => gl_use_default_material(),
+            BlendMode::Additive => gl_use_material(self.additive_material),
+            BlendMode::Multiply => gl_use_material(self.multiply_material),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use camera_layer::effects::{CameraEffects, Easing};
+use macroquad::prelude::{vec2, Camera, Camera2D, Rect, Vec2};
+
+const LAYER_SIZE: Vec2 = Vec2::new(480.0, 640.0);
+
+/// A layer camera that has scrolled away from the origin, set up the same way `CameraLayer::new_with_offset` does
+fn layer_camera() -> (Camera2D, Rect) {
+    let view = Rect::new(1000.0, -300.0, LAYER_SIZE.x, LAYER_SIZE.y);
+    let mut camera = Camera2D::from_display_rect(view);
+    camera.zoom.y = -camera.zoom.y;
+    (camera, view)
+}
+
+/// Where a world point ends up on the render target, in normalized device coordinates
+fn to_screen(camera: &Camera2D, point: Vec2) -> Vec2 {
+    camera.matrix().project_point3(point.extend(0.0)).truncate()
+}
+
+fn assert_near(actual: Vec2, expected: Vec2, context: &str) {
+    assert!(actual.distance(expected) < 1e-4, "{}: expected {} but got {}", context, expected, actual);
+}
+
+#[test]
+fn tweens_reach_their_target_and_stop() {
+    let mut effects = CameraEffects::new();
+    effects.zoom_to(3.0, None, 2.0, Easing::Linear);
+
+    effects.update(0.5);
+    assert!((effects.get_zoom() - 1.5).abs() < 1e-5);
+    effects.update(0.5);
+    assert!((effects.get_zoom() - 2.0).abs() < 1e-5);
+    effects.update(5.0);
+    assert_eq!(effects.get_zoom(), 3.0);
+}
+
+#[test]
+fn zero_duration_tweens_jump_straight_there() {
+    let mut effects = CameraEffects::new();
+    effects.rotate_to(45.0, 0.0, Easing::EaseInOut);
+
+    assert_eq!(effects.get_rotation(), 45.0);
+}
+
+#[test]
+fn retargeting_starts_from_the_current_value() {
+    let mut effects = CameraEffects::new();
+    effects.rotate_to(90.0, 1.0, Easing::Linear);
+    effects.update(0.5);
+
+    // Turning back mid-way shouldn't jump to either end
+    effects.rotate_to(0.0, 1.0, Easing::Linear);
+    assert!((effects.get_rotation() - 45.0).abs() < 1e-4);
+    effects.update(0.5);
+    assert!((effects.get_rotation() - 22.5).abs() < 1e-4);
+}
+
+#[test]
+fn easing_curves_start_and_end_in_place() {
+    for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+        assert_eq!(easing.apply(0.0), 0.0, "{:?}", easing);
+        assert_eq!(easing.apply(1.0), 1.0, "{:?}", easing);
+        assert_eq!(easing.apply(2.0), 1.0, "{:?}", easing);
+    }
+
+    assert!(Easing::EaseIn.apply(0.25) < 0.25);
+    assert!(Easing::EaseOut.apply(0.25) > 0.25);
+    assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+}
+
+#[test]
+fn no_effects_leaves_the_camera_alone() {
+    let (camera, view) = layer_camera();
+    let effects = CameraEffects::new();
+
+    let applied = effects.apply(&camera, view);
+    assert_eq!(applied.target, camera.target);
+    assert_eq!(applied.zoom, camera.zoom);
+    assert_eq!(applied.rotation, camera.rotation);
+}
+
+#[test]
+fn zoom_point_stays_still_on_screen() {
+    let (camera, view) = layer_camera();
+    let point = vec2(1100.0, -250.0);
+
+    let mut effects = CameraEffects::new();
+    effects.zoom_to(2.5, Some(point), 0.0, Easing::Linear);
+    let applied = effects.apply(&camera, view);
+
+    assert_near(to_screen(&applied, point), to_screen(&camera, point), "zoom point");
+
+    // Everything else spreads out from it
+    let other = point + vec2(10.0, 20.0);
+    let before = to_screen(&camera, other) - to_screen(&camera, point);
+    let after = to_screen(&applied, other) - to_screen(&applied, point);
+    assert_near(after, before * 2.5, "zoomed offset");
+}
+
+#[test]
+fn zoom_without_a_point_closes_in_on_the_middle() {
+    let (camera, view) = layer_camera();
+
+    let mut effects = CameraEffects::new();
+    effects.zoom_to(0.5, None, 0.0, Easing::Linear);
+    let applied = effects.apply(&camera, view);
+
+    assert_near(to_screen(&applied, view.center()), to_screen(&camera, view.center()), "view center");
+}
+
+#[test]
+fn rotation_turns_around_the_middle_of_the_view() {
+    let (camera, view) = layer_camera();
+
+    let mut effects = CameraEffects::new();
+    effects.zoom_to(2.0, None, 0.0, Easing::Linear);
+    effects.rotate_to(30.0, 0.0, Easing::Linear);
+    let applied = effects.apply(&camera, view);
+
+    let center = view.center();
+    assert_near(to_screen(&applied, center), to_screen(&camera, center), "view center");
+
+    // Distances from the middle are only scaled, whichever way the turn goes
+    let other = center + vec2(40.0, -25.0);
+    let before = (to_screen(&camera, other) - to_screen(&camera, center)) * LAYER_SIZE;
+    let after = (to_screen(&applied, other) - to_screen(&applied, center)) * LAYER_SIZE;
+    assert!((after.length() - before.length() * 2.0).abs() < 1e-2);
+    assert!(before.angle_between(after).abs() > 0.1);
+}
+
+#[test]
+fn shake_wears_off() {
+    let (camera, view) = layer_camera();
+
+    let mut effects = CameraEffects::new();
+    effects.add_trauma(2.0);
+    assert_eq!(effects.get_trauma(), 1.0);
+
+    effects.update(0.1);
+    let shaken = effects.apply(&camera, view);
+    assert!(shaken.target != camera.target || shaken.rotation != camera.rotation);
+
+    effects.update(10.0);
+    assert_eq!(effects.get_trauma(), 0.0);
+    assert_eq!(effects.apply(&camera, view).target, camera.target);
+}
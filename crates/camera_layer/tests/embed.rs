@@ -0,0 +1,102 @@
+use camera_layer::embed::LayerQuad;
+use macroquad::prelude::{vec2, Rect, Vec2};
+
+/// A screen seen at an angle, with its right edge further away than its left
+fn perspective_quad() -> LayerQuad {
+    LayerQuad::new(vec2(100.0, 50.0), vec2(300.0, 90.0), vec2(300.0, 210.0), vec2(100.0, 250.0))
+}
+
+const SAMPLES: [Vec2; 6] = [
+    Vec2::new(0.0, 0.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(0.5, 0.5),
+    Vec2::new(0.25, 0.75),
+    Vec2::new(0.9, 0.1),
+    Vec2::new(1.0, 0.0),
+];
+
+fn assert_near(actual: Vec2, expected: Vec2, context: &str) {
+    assert!(actual.distance(expected) < 1e-3, "{}: expected {} but got {}", context, expected, actual);
+}
+
+#[test]
+fn rects_map_linearly() {
+    let quad = LayerQuad::from_rect(Rect::new(-40.0, 20.0, 160.0, 90.0));
+
+    assert_near(quad.to_host(Vec2::ZERO), vec2(-40.0, 20.0), "top left");
+    assert_near(quad.to_host(Vec2::ONE), vec2(120.0, 110.0), "bottom right");
+    assert_near(quad.to_host(vec2(0.25, 0.5)), vec2(0.0, 65.0), "inside");
+}
+
+#[test]
+fn rects_round_trip() {
+    let quad = LayerQuad::from_rect(Rect::new(-40.0, 20.0, 160.0, 90.0));
+
+    for uv in SAMPLES {
+        let point = quad.to_host(uv);
+        assert_near(quad.to_uv(point).unwrap(), uv, &format!("{}", uv));
+    }
+}
+
+#[test]
+fn corners_land_where_they_were_given() {
+    let quad = perspective_quad();
+    let [top_left, top_right, bottom_right, bottom_left] = quad.get_corners();
+
+    assert_near(quad.to_host(vec2(0.0, 0.0)), top_left, "top left");
+    assert_near(quad.to_host(vec2(1.0, 0.0)), top_right, "top right");
+    assert_near(quad.to_host(vec2(1.0, 1.0)), bottom_right, "bottom right");
+    assert_near(quad.to_host(vec2(0.0, 1.0)), bottom_left, "bottom left");
+}
+
+#[test]
+fn perspective_quads_round_trip() {
+    let quad = perspective_quad();
+
+    for uv in SAMPLES {
+        let point = quad.to_host(uv);
+        assert_near(quad.to_uv(point).unwrap(), uv, &format!("{}", uv));
+    }
+}
+
+#[test]
+fn perspective_squeezes_the_far_side() {
+    let quad = perspective_quad();
+
+    // The layer's middle is drawn nearer the far edge than the quad's middle is
+    let middle = quad.to_host(vec2(0.5, 0.5));
+    assert!(middle.x > 200.0 + 1.0, "expected the middle right of x = 200, got {}", middle);
+    assert_near(vec2(0.0, middle.y), vec2(0.0, 150.0), "symmetric quads keep the middle row");
+}
+
+#[test]
+fn points_outside_are_rejected() {
+    for quad in [LayerQuad::from_rect(Rect::new(0.0, 0.0, 100.0, 50.0)), perspective_quad()] {
+        let [top_left, _, bottom_right, _] = quad.get_corners();
+
+        assert!(quad.to_uv(top_left - vec2(1.0, 0.0)).is_none());
+        assert!(quad.to_uv(bottom_right + vec2(0.0, 1.0)).is_none());
+        assert!(quad.to_uv(vec2(1000.0, 1000.0)).is_none());
+        assert!(quad.to_uv((top_left + bottom_right) * 0.5).is_some());
+    }
+}
+
+#[test]
+fn points_on_the_edge_are_inside() {
+    let quad = perspective_quad();
+
+    let edge = quad.to_host(vec2(1.0, 0.5));
+    let uv = quad.to_uv(edge).unwrap();
+    assert!(uv.x <= 1.0 && uv.y <= 1.0, "uv should be clamped onto the layer, got {}", uv);
+    assert_near(uv, vec2(1.0, 0.5), "right edge");
+}
+
+#[test]
+fn parallelograms_are_affine() {
+    assert!(LayerQuad::from_rect(Rect::new(10.0, 10.0, 64.0, 32.0)).is_affine());
+
+    let sheared = LayerQuad::new(vec2(0.0, 0.0), vec2(100.0, 20.0), vec2(130.0, 80.0), vec2(30.0, 60.0));
+    assert!(sheared.is_affine());
+
+    assert!(!perspective_quad().is_affine());
+}
@@ -0,0 +1,118 @@
+use camera_layer::follow::CameraFollow;
+use macroquad::prelude::{vec2, Rect, Vec2};
+
+// A 100x100 view centered on (50, 50)
+const VIEW: Rect = Rect { x: 0.0, y: 0.0, w: 100.0, h: 100.0 };
+
+fn assert_near(actual: Vec2, expected: Vec2) {
+    assert!(actual.distance(expected) < 1e-4, "expected {} but got {}", expected, actual);
+}
+
+/// A follow with a 20x20 deadzone around the view's center
+fn deadzone_follow() -> CameraFollow {
+    CameraFollow::new(Rect::new(-10.0, -10.0, 20.0, 20.0))
+}
+
+#[test]
+fn targets_inside_the_deadzone_leave_the_camera_alone() {
+    let mut follow = deadzone_follow();
+
+    assert_near(follow.update(VIEW, vec2(55.0, 42.0), Vec2::ZERO, 0.1), vec2(50.0, 50.0));
+    assert_near(follow.update(VIEW, vec2(60.0, 60.0), Vec2::ZERO, 0.1), vec2(50.0, 50.0));
+}
+
+#[test]
+fn targets_leaving_the_deadzone_drag_it_along() {
+    let mut follow = deadzone_follow();
+
+    // Just far enough that the target sits on the deadzone's edge
+    assert_near(follow.update(VIEW, vec2(75.0, 50.0), Vec2::ZERO, 0.1), vec2(65.0, 50.0));
+    assert_near(follow.update(VIEW, vec2(75.0, 20.0), Vec2::ZERO, 0.1), vec2(65.0, 30.0));
+}
+
+#[test]
+fn lookahead_leads_the_target_by_its_velocity() {
+    let mut follow = deadzone_follow();
+    follow.set_lookahead(0.2);
+
+    assert_near(follow.update(VIEW, vec2(50.0, 50.0), vec2(100.0, -150.0), 0.1), vec2(60.0, 30.0));
+}
+
+#[test]
+fn smoothing_eases_towards_the_target() {
+    let mut follow = CameraFollow::new(Rect::new(0.0, 0.0, 0.0, 0.0));
+    follow.set_smoothing(10.0);
+
+    let center = follow.update(VIEW, vec2(150.0, 50.0), Vec2::ZERO, 0.1);
+    let expected = 50.0 + 100.0 * (1.0 - (-1.0f32).exp());
+    assert_near(center, vec2(expected, 50.0));
+
+    // Keeps closing in without overshooting
+    let mut previous = center.x;
+    for _ in 0..20 {
+        let center = follow.update(VIEW, vec2(150.0, 50.0), Vec2::ZERO, 0.1);
+        assert!(center.x >= previous && center.x <= 150.0);
+        previous = center.x;
+    }
+    assert!((150.0 - previous).abs() < 1e-3);
+}
+
+#[test]
+fn smoothing_is_independent_of_frame_rate() {
+    let mut once = CameraFollow::new(Rect::new(0.0, 0.0, 0.0, 0.0));
+    let mut twice = once.clone();
+    once.set_smoothing(4.0);
+    twice.set_smoothing(4.0);
+
+    let target = vec2(130.0, -10.0);
+    let whole = once.update(VIEW, target, Vec2::ZERO, 0.1);
+    twice.update(VIEW, target, Vec2::ZERO, 0.05);
+    let halves = twice.update(VIEW, target, Vec2::ZERO, 0.05);
+
+    assert_near(halves, whole);
+}
+
+#[test]
+fn bounds_keep_the_view_inside_the_world() {
+    let mut follow = CameraFollow::new(Rect::new(0.0, 0.0, 0.0, 0.0));
+    follow.set_bounds(Some(Rect::new(0.0, 0.0, 300.0, 200.0)));
+
+    assert_near(follow.update(VIEW, vec2(-500.0, 80.0), Vec2::ZERO, 0.1), vec2(50.0, 80.0));
+    assert_near(follow.update(VIEW, vec2(1000.0, 1000.0), Vec2::ZERO, 0.1), vec2(250.0, 150.0));
+}
+
+#[test]
+fn bounds_smaller_than_the_view_center_it() {
+    let mut follow = CameraFollow::new(Rect::new(0.0, 0.0, 0.0, 0.0));
+    follow.set_bounds(Some(Rect::new(20.0, 0.0, 60.0, 400.0)));
+
+    assert_near(follow.update(VIEW, vec2(300.0, 120.0), Vec2::ZERO, 0.1), vec2(50.0, 120.0));
+}
+
+#[test]
+fn pixel_snap_rounds_without_losing_slow_movement() {
+    let mut follow = CameraFollow::new(Rect::new(0.0, 0.0, 0.0, 0.0));
+    follow.set_pixel_snap(true);
+    follow.set_smoothing(3.0);
+
+    // Each step moves well under half a unit, which rounding alone would throw away every frame
+    let mut center = Vec2::ZERO;
+    for _ in 0..20 {
+        center = follow.update(VIEW, vec2(51.0, 50.0), Vec2::ZERO, 0.05);
+        assert_eq!(center, center.round());
+    }
+    assert_eq!(center, vec2(51.0, 50.0));
+}
+
+#[test]
+fn reset_starts_again_from_the_view() {
+    let mut follow = CameraFollow::new(Rect::new(0.0, 0.0, 0.0, 0.0));
+    follow.set_smoothing(1.0);
+    follow.update(VIEW, vec2(150.0, 50.0), Vec2::ZERO, 0.5);
+
+    follow.reset();
+    // No time passes, so the camera stays wherever the view now is
+    let moved_view = Rect::new(-50.0, 0.0, 100.0, 100.0);
+    let center = follow.update(moved_view, Vec2::ZERO, Vec2::ZERO, 0.0);
+    assert_near(center, moved_view.center());
+}
@@ -0,0 +1,141 @@
+use camera_layer::{presentation::Presentation, ScalingMode};
+use macroquad::prelude::{vec2, Camera2D, Rect, Vec2};
+
+const LAYER_SIZE: Vec2 = Vec2::new(480.0, 640.0);
+
+// Window sizes wider, taller, smaller and stranger than the layer
+const WINDOW_SIZES: [Vec2; 6] = [
+    Vec2::new(480.0, 640.0),
+    Vec2::new(1920.0, 1080.0),
+    Vec2::new(1000.0, 700.0),
+    Vec2::new(333.0, 1777.0),
+    Vec2::new(200.0, 150.0),
+    Vec2::new(2561.0, 1439.0),
+];
+
+const SCALING_MODES: [ScalingMode; 4] = [ScalingMode::Integer, ScalingMode::Fractional, ScalingMode::Stretch, ScalingMode::Crop];
+
+/// A camera set up the same way `CameraLayer::new_with_offset` does, without needing a render target
+fn layer_camera(offset: Vec2) -> Camera2D {
+    let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, LAYER_SIZE.x, LAYER_SIZE.y));
+    camera.offset = offset;
+    camera.zoom.y = -camera.zoom.y;
+    camera
+}
+
+fn assert_near(actual: Vec2, expected: Vec2, context: &str) {
+    assert!(actual.distance(expected) < 1e-2, "{}: expected {} but got {}", context, expected, actual);
+}
+
+#[test]
+fn fit_letterboxes_wide_windows() {
+    let presentation = Presentation::fit(LAYER_SIZE, vec2(1920.0, 1080.0), ScalingMode::Fractional);
+
+    assert_eq!(presentation.get_rect(), Rect::new(555.0, 0.0, 810.0, 1080.0));
+    assert_eq!(presentation.get_scale(), Vec2::splat(1.6875));
+}
+
+#[test]
+fn fit_integer_floors_scale_and_padding() {
+    let presentation = Presentation::fit(LAYER_SIZE, vec2(1000.0, 1333.0), ScalingMode::Integer);
+
+    assert_eq!(presentation.get_scale(), Vec2::splat(2.0));
+    assert_eq!(presentation.get_rect(), Rect::new(20.0, 26.0, 960.0, 1280.0));
+}
+
+#[test]
+fn fit_integer_falls_back_when_window_is_smaller() {
+    let presentation = Presentation::fit(LAYER_SIZE, vec2(200.0, 150.0), ScalingMode::Integer);
+
+    assert_eq!(presentation.get_scale(), Vec2::splat(150.0 / 640.0));
+    assert_eq!(presentation.get_rect().size(), vec2(112.5, 150.0));
+}
+
+#[test]
+fn fit_crop_overflows_the_window() {
+    let presentation = Presentation::fit(LAYER_SIZE, vec2(1920.0, 1080.0), ScalingMode::Crop);
+
+    assert_eq!(presentation.get_rect(), Rect::new(0.0, -740.0, 1920.0, 2560.0));
+}
+
+#[test]
+fn fit_stretch_fills_the_window() {
+    let presentation = Presentation::fit(LAYER_SIZE, vec2(333.0, 1777.0), ScalingMode::Stretch);
+
+    assert_eq!(presentation.get_rect(), Rect::new(0.0, 0.0, 333.0, 1777.0));
+}
+
+#[test]
+fn window_corners_of_the_layer_map_to_render_target_corners() {
+    for window in WINDOW_SIZES {
+        for mode in SCALING_MODES {
+            let presentation = Presentation::fit(LAYER_SIZE, window, mode);
+            let rect = presentation.get_rect();
+            let context = format!("{:?} in {}", mode, window);
+
+            assert_near(presentation.window_to_layer(rect.point()), Vec2::ZERO, &context);
+            assert_near(presentation.window_to_layer(rect.point() + rect.size()), LAYER_SIZE, &context);
+            assert_near(presentation.window_to_layer(rect.center()), LAYER_SIZE * 0.5, &context);
+        }
+    }
+}
+
+#[test]
+fn bars_are_not_part_of_the_layer() {
+    let presentation = Presentation::fit(LAYER_SIZE, vec2(1920.0, 1080.0), ScalingMode::Fractional);
+
+    assert!(!presentation.contains(vec2(100.0, 540.0)));
+    assert!(!presentation.contains(vec2(1800.0, 540.0)));
+    assert!(presentation.contains(vec2(960.0, 540.0)));
+
+    let layer = presentation.window_to_layer(vec2(100.0, 540.0));
+    assert!(layer.x < 0.0, "A pixel in the left bar should be left of the layer, got {}", layer);
+}
+
+#[test]
+fn window_to_world_matches_the_view() {
+    let camera = layer_camera(Vec2::ZERO);
+
+    for window in WINDOW_SIZES {
+        for mode in SCALING_MODES {
+            let presentation = Presentation::fit(LAYER_SIZE, window, mode);
+            let rect = presentation.get_rect();
+            let context = format!("{:?} in {}", mode, window);
+
+            // With no offset the camera shows exactly the display rect it was made from
+            assert_near(presentation.window_to_world(&camera, rect.point()), Vec2::ZERO, &context);
+            assert_near(presentation.window_to_world(&camera, rect.point() + rect.size()), LAYER_SIZE, &context);
+            assert_near(presentation.window_to_world(&camera, rect.point() + rect.size() * vec2(0.25, 0.75)), LAYER_SIZE * vec2(0.25, 0.75), &context);
+        }
+    }
+}
+
+#[test]
+fn world_round_trips_through_the_window() {
+    let mut camera = layer_camera(vec2(1.0, 1.0));
+    camera.target = vec2(1234.5, -321.0);
+
+    for window in WINDOW_SIZES {
+        for mode in SCALING_MODES {
+            let presentation = Presentation::fit(LAYER_SIZE, window, mode);
+            let context = format!("{:?} in {}", mode, window);
+
+            for point in [vec2(1234.5, -321.0), vec2(1000.0, -600.0), vec2(1500.25, 10.75)] {
+                let window_point = presentation.world_to_window(&camera, point);
+                assert_near(presentation.window_to_world(&camera, window_point), point, &context);
+            }
+        }
+    }
+}
+
+#[test]
+fn one_world_unit_is_one_scaled_pixel() {
+    let camera = layer_camera(Vec2::ZERO);
+    let presentation = Presentation::fit(LAYER_SIZE, vec2(1000.0, 1333.0), ScalingMode::Integer);
+
+    let first = presentation.world_to_window(&camera, vec2(100.0, 100.0));
+    let second = presentation.world_to_window(&camera, vec2(101.0, 102.0));
+
+    assert_near(second - first, vec2(2.0, 4.0), "integer scale of 2");
+    assert_near(first, vec2(220.0, 226.0), "integer scale of 2");
+}
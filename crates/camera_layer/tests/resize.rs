@@ -0,0 +1,84 @@
+use camera_layer::{presentation::Presentation, ResizePolicy, ScalingMode};
+use macroquad::prelude::{uvec2, vec2, Vec2};
+
+const AREA: Vec2 = Vec2::new(480.0, 640.0);
+
+#[test]
+fn fixed_layers_keep_their_size() {
+    assert_eq!(ResizePolicy::Fixed.get_layout(vec2(1920.0, 1080.0), ScalingMode::Integer, 1.0), None);
+}
+
+#[test]
+fn virtual_resolution_renders_at_screen_size() {
+    let policy = ResizePolicy::VirtualResolution(AREA);
+
+    assert_eq!(policy.get_layout(vec2(1920.0, 1080.0), ScalingMode::Fractional, 1.0), Some((uvec2(810, 1080), AREA)));
+    assert_eq!(policy.get_layout(vec2(1000.0, 1333.0), ScalingMode::Integer, 1.0), Some((uvec2(960, 1280), AREA)));
+    assert_eq!(policy.get_layout(vec2(333.0, 1777.0), ScalingMode::Stretch, 1.0), Some((uvec2(333, 1777), AREA)));
+}
+
+#[test]
+fn render_scale_changes_resolution_but_not_area() {
+    let policy = ResizePolicy::VirtualResolution(AREA);
+    assert_eq!(policy.get_layout(vec2(1920.0, 1080.0), ScalingMode::Fractional, 0.5), Some((uvec2(405, 540), AREA)));
+
+    let policy = ResizePolicy::MinimumArea(AREA);
+    let (low, low_view) = policy.get_layout(vec2(1000.0, 1333.0), ScalingMode::Integer, 1.0).unwrap();
+    let (high, high_view) = policy.get_layout(vec2(1000.0, 1333.0), ScalingMode::Integer, 2.0).unwrap();
+    assert_eq!(high, low * 2);
+    assert_eq!(high_view, low_view);
+}
+
+#[test]
+fn minimum_area_shows_more_of_the_world_on_the_long_side() {
+    let policy = ResizePolicy::MinimumArea(AREA);
+
+    for (window, mode) in [
+        (vec2(1920.0, 1080.0), ScalingMode::Fractional),
+        (vec2(1920.0, 1080.0), ScalingMode::Integer),
+        (vec2(1000.0, 700.0), ScalingMode::Crop),
+        (vec2(333.0, 1777.0), ScalingMode::Stretch),
+        (vec2(200.0, 150.0), ScalingMode::Integer),
+    ] {
+        let (resolution, view) = policy.get_layout(window, mode, 1.0).unwrap();
+        let context = format!("{:?} in {}", mode, window);
+
+        assert!(view.cmpge(AREA).all(), "{}: only shows {}", context, view);
+        assert_eq!(resolution.as_vec2(), view, "{}: one pixel per world unit", context);
+
+        // Whichever side is longer relative to the area gets the extra, the other matches it
+        let scale = window / view;
+        assert!((scale.x - scale.y).abs() / scale.x < 0.01, "{}: stretched by {}", context, scale);
+    }
+}
+
+#[test]
+fn minimum_area_integer_scales_by_whole_pixels() {
+    let policy = ResizePolicy::MinimumArea(AREA);
+
+    assert_eq!(policy.get_layout(vec2(1920.0, 1080.0), ScalingMode::Integer, 1.0), Some((uvec2(1920, 1080), vec2(1920.0, 1080.0))));
+    assert_eq!(policy.get_layout(vec2(1920.0, 1300.0), ScalingMode::Integer, 1.0), Some((uvec2(960, 650), vec2(960.0, 650.0))));
+
+    // Odd windows leave their spare pixel empty instead of rounding the layer up past a scale of 2
+    assert_eq!(policy.get_layout(vec2(1921.0, 1301.0), ScalingMode::Integer, 1.0), Some((uvec2(960, 650), vec2(960.0, 650.0))));
+
+    let presentation = Presentation::fit(vec2(960.0, 650.0), vec2(1921.0, 1301.0), policy.get_presentation_mode(ScalingMode::Integer));
+    assert_eq!(presentation.get_scale(), Vec2::splat(2.0));
+}
+
+#[test]
+fn minimum_area_fractional_fills_the_window() {
+    let policy = ResizePolicy::MinimumArea(AREA);
+
+    for window in [vec2(1921.0, 1301.0), vec2(1000.0, 700.0), vec2(333.0, 1777.0), vec2(2561.0, 1439.0)] {
+        let (resolution, _) = policy.get_layout(window, ScalingMode::Fractional, 1.0).unwrap();
+        let presentation = Presentation::fit(resolution.as_vec2(), window, policy.get_presentation_mode(ScalingMode::Fractional));
+
+        let rect = presentation.get_rect();
+        assert!(rect.point().length() < 1e-3 && rect.size().distance(window) < 1e-3, "{}: left a bar, drawn at {:?}", window, rect);
+
+        // Filling it can only stretch by the part of a layer pixel that was rounded up
+        let scale = presentation.get_scale();
+        assert!((scale.x / scale.y - 1.0).abs() * resolution.as_vec2().min_element() < 1.0, "{}: stretched by {}", window, scale);
+    }
+}
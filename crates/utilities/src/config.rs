@@ -8,6 +8,12 @@ pub struct ConfigSettings {
     config: Map<String, ConfigMap>
 }
 
+impl Default for ConfigSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ConfigSettings {
     pub fn new() -> Self {
         ConfigSettings {
@@ -35,17 +41,14 @@ impl ConfigSettings {
     }
 
     pub fn get_int(&self, config_key: &str, category: &str, key: &str) -> Result<i64, String> {
-        match self.config.get(&config_key.to_owned()) {
-            Some(config_map) => match config_map.0.get(&category.to_owned()) {
-                Some(section) => match section.get(&key.to_owned()){
-                    Some(val) => match val {
-                        Some(inner) => match inner.parse::<i64>() {
-                            Ok(int) => Ok(int),
-                            Err(why) => Err(format!("Unable to parse config value ({}/{}/{}) as int: {}", config_key, category, key, why)),
-                        },
-                        None => Err(format!("Unable to find config value ({}/{}) within config: {}", category, key, config_key))
+        match self.config.get(config_key) {
+            Some(config_map) => match config_map.0.get(category) {
+                Some(section) => match section.get(key){
+                    Some(Some(inner)) => match inner.parse::<i64>() {
+                        Ok(int) => Ok(int),
+                        Err(why) => Err(format!("Unable to parse config value ({}/{}/{}) as int: {}", config_key, category, key, why)),
                     },
-                    None => Err(format!("Unable to find config value ({}/{}) within config: {}", category, key, config_key))
+                    _ => Err(format!("Unable to find config value ({}/{}) within config: {}", category, key, config_key))
                 },
                 None => Err(format!("Unable to find section ({}) within config: {}", category, config_key))
             },
@@ -5,6 +5,12 @@ pub struct InfiniteGrid<T> {
     map: HashMap<u64, T>,
 }
 
+impl<T> Default for InfiniteGrid<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> InfiniteGrid<T> {
     pub fn new() -> Self {
         InfiniteGrid::<T> {
@@ -27,11 +33,42 @@ impl<T> InfiniteGrid<T> {
         self.map.insert(hash, value);
     }
 
+    pub fn remove(&mut self, x: i32, y: i32) -> Option<T> {
+        let hash = Self::to_hash(x, y);
+        self.map.remove(&hash)
+    }
+
+    #[inline]
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.map.contains_key(&Self::to_hash(x, y))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterate over every occupied location along with its coordinates, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), &T)> {
+        self.map.iter().map(|(hash, value)| (Self::from_hash(*hash), value))
+    }
+
+    /// The coordinates of every occupied location, in no particular order
+    pub fn coords(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.map.keys().map(|hash| Self::from_hash(*hash))
+    }
+
     pub fn to_hash(x: i32, y:i32) -> u64 {
-        let ux: u64 = x as u64;
-        let uy: u64 = y as u64;
+        // Go through u32 so negative coordinates don't sign extend into the y half
+        let ux: u64 = x as u32 as u64;
+        let uy: u64 = y as u32 as u64;
         let sy = uy << 32;
-        ux.wrapping_add(sy)
+        ux | sy
     }
 
     pub fn from_hash(hash: u64) -> (i32, i32) {
@@ -39,4 +76,4 @@ impl<T> InfiniteGrid<T> {
         let uy = (hash >> 32) as u32;
         (ux as i32, uy as i32)
     }
-}
\ No newline at end of file
+}
@@ -1,11 +1,45 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
+use std::mem::size_of;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
-use crate::tileset::{WaveFunctionTileHandle, WaveFunctionTileset};
-use macroquad::rand::RandomRange;
+use crate::{
+    generator::{GeneratedSector, SectorGenerator, SectorRequest},
+    property::TileProperty,
+    tileset::{WaveFunctionEdgeHandle, WaveFunctionTextureHandle, WaveFunctionTileHandle, WaveFunctionTileset}
+};
+use macroquad::{logging::warn, rand::RandGenerator};
 use utilities::infinite_grid::InfiniteGrid;
 
+// Neighbour offsets indexed by the tileset's DIRECTION_* constants
+const DIRECTION_OFFSETS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+const MAX_GENERATION_ATTEMPTS: u32 = 8;
+
+// Salts that keep the random streams used for sector interiors, seams and corners apart
+const SALT_SECTOR: u64 = 0x5345_4354_4f52_0000;
+const SALT_SEAM_HORIZONTAL: u64 = 0x5345_414d_0048_0000;
+const SALT_SEAM_VERTICAL: u64 = 0x5345_414d_0056_0000;
+const SALT_CORNER: u64 = 0x434f_524e_4552_0000;
+
+// SplitMix64 finalizer, spreads nearby sector coordinates across the whole seed space
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn seeded_rng(seed: u64, salt: u64, x: i32, y: i32) -> RandGenerator {
+    let rng = RandGenerator::new();
+    rng.srand(mix(seed ^ mix(salt ^ InfiniteGrid::<()>::to_hash(x, y))));
+    rng
+}
+
 pub struct WaveFunctionCell {
-    states: HashSet<WaveFunctionTileHandle>,
+    states: BTreeSet<WaveFunctionTileHandle>,
 }
 
 impl WaveFunctionCell {
@@ -17,7 +51,7 @@ impl WaveFunctionCell {
     }
 
     pub fn new_collapsed(state: WaveFunctionTileHandle) -> Self {
-        let mut states = HashSet::new();
+        let mut states = BTreeSet::new();
         states.insert(state);
 
         WaveFunctionCell {
@@ -30,20 +64,18 @@ impl WaveFunctionCell {
         self.states.len()
     }
 
-    pub fn collapse(&mut self, tileset: &WaveFunctionTileset) {
+    pub fn collapse(&mut self, tileset: &WaveFunctionTileset, rng: &RandGenerator) {
         let mut collapse_selector: Vec<(WaveFunctionTileHandle, f32)> = Vec::new();
         let mut running_weight: f32 = 0.0;
 
         // Select a random state and disregard the others, use the weights from the tileset to select
         for tile_handle in self.states.iter() {
-            if let Some(class) = tileset.get_class_from_tile(tile_handle) {
-                let weight = tileset.get_weight(&class);
-                running_weight += weight;
-                collapse_selector.push((*tile_handle, weight));
-            }
+            let weight = tileset.get_tile_weight(tile_handle);
+            running_weight += weight;
+            collapse_selector.push((*tile_handle, weight));
         }
 
-        let mut selected_weight = f32::gen_range(0.0, running_weight);
+        let mut selected_weight = rng.gen_range(0.0, running_weight);
         let selected = collapse_selector.iter().find(|&data| {
             selected_weight -= data.1;
             selected_weight <= 0.0
@@ -65,13 +97,20 @@ impl WaveFunctionCell {
             None
         }
     }
+
+    fn get_memory_usage(&self) -> usize {
+        size_of::<Self>() + self.states.len() * size_of::<WaveFunctionTileHandle>()
+    }
 }
 
 pub struct WaveFunctionSector {
-    // width: usize,
-    // height: usize,
+    width: usize,
+    height: usize,
 
     cells: Vec<WaveFunctionCell>,
+
+    // Set once anything other than the generator has written to the sector
+    modified: bool,
 }
 
 impl WaveFunctionSector {
@@ -84,62 +123,701 @@ impl WaveFunctionSector {
             cells.push(WaveFunctionCell::new_empty(tileset));
         }
 
-    WaveFunctionSector {
-            // width,
-            // height,
+        WaveFunctionSector {
+            width,
+            height,
             cells,
+            modified: false,
+        }
+    }
+
+    fn clone_cells(&self) -> Self {
+        WaveFunctionSector {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(|cell| WaveFunctionCell { states: cell.states.clone() }).collect(),
+            modified: self.modified,
+        }
+    }
+
+    fn from_tiles(tileset: &WaveFunctionTileset, width: usize, height: usize, tiles: &[Option<WaveFunctionTileHandle>]) -> Self {
+        let cells = tiles.iter().map(|tile| match tile {
+            Some(handle) => WaveFunctionCell::new_collapsed(*handle),
+            None => WaveFunctionCell::new_empty(tileset),
+        }).collect();
+
+        WaveFunctionSector {
+            width,
+            height,
+            cells,
+            modified: true,
+        }
+    }
+
+    /// Generate the sector at the given coordinates. The result only depends on the tileset, the seed and the coordinates,
+    /// so an evicted sector can be regenerated exactly and sectors can be generated in any order.
+    pub fn generate(tileset: &WaveFunctionTileset, seed: u64, x: i32, y: i32, width: usize, height: usize) -> Self {
+        let rng = seeded_rng(seed, SALT_SECTOR, x, y);
+
+        let mut border = Self::new(tileset, width, height);
+        let has_border = border.apply_border(tileset, seed, x, y);
+        if !has_border {
+            warn!("Sector ({:?},{:?}) has a contradictory border, its seams may not match its neighbours", x, y);
+        }
+
+        for attempt in 0..MAX_GENERATION_ATTEMPTS {
+            // Give up on the border for the later attempts so one unlucky border can't stall generation
+            if has_border && attempt == MAX_GENERATION_ATTEMPTS / 2 {
+                warn!("Unable to solve sector ({:?},{:?}) within its border, its seams may not match its neighbours", x, y);
+            }
+
+            let mut sector = if has_border && attempt < MAX_GENERATION_ATTEMPTS / 2 {
+                border.clone_cells()
+            } else {
+                Self::new(tileset, width, height)
+            };
+
+            if sector.solve(tileset, &rng) {
+                return sector;
+            }
+        }
+
+        warn!("Unable to solve sector ({:?},{:?}), leaving contradictions unresolved", x, y);
+
+        let mut sector = Self::new(tileset, width, height);
+        sector.solve(tileset, &rng);
+        sector
+    }
+
+    /// Fix the ring of cells around the edge of the sector to the tiles shared with the neighbouring sectors.
+    /// Returns false if the border contradicts itself.
+    fn apply_border(&mut self, tileset: &WaveFunctionTileset, seed: u64, x: i32, y: i32) -> bool {
+        let (width, height) = (self.width, self.height);
+        if width < 2 || height < 2 {
+            return true;
+        }
+
+        let mut touched = Vec::new();
+
+        // Every corner is shared by four sectors, so each one is a tiny 2x2 solve that all four agree on.
+        // The tuples are the corner point, the index of our cell within its block and our cell coordinates.
+        let corners = [
+            (x, y, 3, 0, 0),
+            (x + 1, y, 2, width - 1, 0),
+            (x, y + 1, 1, 0, height - 1),
+            (x + 1, y + 1, 0, width - 1, height - 1),
+        ];
+
+        for (corner_x, corner_y, block_index, cell_x, cell_y) in corners {
+            if let Some(tile) = Self::solve_corner(tileset, seed, corner_x, corner_y)[block_index] {
+                let index = self.get_index(cell_x, cell_y);
+                self.cells[index] = WaveFunctionCell::new_collapsed(tile);
+                touched.push(index);
+            }
+        }
+
+        // Each seam is a strip two cells deep straddling the boundary with a neighbour, pinned to the corners at either end.
+        // The tuples are the seam coordinates, whether it runs horizontally, the strip row or column we take and where it goes.
+        let seams = [
+            (x, y, true, 1, 0),
+            (x, y + 1, true, 0, height - 1),
+            (x, y, false, 1, 0),
+            (x + 1, y, false, 0, width - 1),
+        ];
+
+        for (seam_x, seam_y, horizontal, strip_line, line) in seams {
+            if let Some(strip) = Self::solve_seam(tileset, seed, seam_x, seam_y, width, height, horizontal) {
+                let length = if horizontal { width } else { height };
+                for i in 1..length - 1 {
+                    let (strip_x, strip_y, cell_x, cell_y) = if horizontal {
+                        (i, strip_line, i, line)
+                    } else {
+                        (strip_line, i, line, i)
+                    };
+
+                    if let Some(tile) = strip.get_tile(strip_x, strip_y) {
+                        let index = self.get_index(cell_x, cell_y);
+                        self.cells[index] = WaveFunctionCell::new_collapsed(tile);
+                        touched.push(index);
+                    }
+                }
+            }
+        }
+
+        self.propagate(tileset, touched)
+    }
+
+    fn solve_corner(tileset: &WaveFunctionTileset, seed: u64, x: i32, y: i32) -> [Option<WaveFunctionTileHandle>; 4] {
+        let rng = seeded_rng(seed, SALT_CORNER, x, y);
+
+        for _ in 0..MAX_GENERATION_ATTEMPTS {
+            let mut block = Self::new(tileset, 2, 2);
+            if block.solve(tileset, &rng) {
+                return [0, 1, 2, 3].map(|index| block.cells[index].get_tile_data());
+            }
+        }
+
+        [None; 4]
+    }
+
+    /// Solve the strip along the top (horizontal) or left edge of the sector at the given coordinates,
+    /// covering the last line of the neighbouring sector and the first line of this one
+    fn solve_seam(tileset: &WaveFunctionTileset, seed: u64, x: i32, y: i32, width: usize, height: usize, horizontal: bool) -> Option<Self> {
+        let (salt, strip_width, strip_height) = if horizontal {
+            (SALT_SEAM_HORIZONTAL, width, 2)
+        } else {
+            (SALT_SEAM_VERTICAL, 2, height)
+        };
+        let rng = seeded_rng(seed, salt, x, y);
+
+        // The corner blocks at either end, with the block indices of the cells that land in the strip
+        let (start, end) = if horizontal {
+            ((x, y, [1, 3]), (x + 1, y, [0, 2]))
+        } else {
+            ((x, y, [2, 3]), (x, y + 1, [0, 1]))
+        };
+
+        let mut pinned = Self::new(tileset, strip_width, strip_height);
+        let mut touched = Vec::new();
+
+        for ((corner_x, corner_y, block_indices), at_start) in [(start, true), (end, false)] {
+            let block = Self::solve_corner(tileset, seed, corner_x, corner_y);
+
+            for (offset, block_index) in block_indices.into_iter().enumerate() {
+                let (cell_x, cell_y) = match (horizontal, at_start) {
+                    (true, true) => (0, offset),
+                    (true, false) => (strip_width - 1, offset),
+                    (false, true) => (offset, 0),
+                    (false, false) => (offset, strip_height - 1),
+                };
+
+                if let Some(tile) = block[block_index] {
+                    let index = pinned.get_index(cell_x, cell_y);
+                    pinned.cells[index] = WaveFunctionCell::new_collapsed(tile);
+                    touched.push(index);
+                }
+            }
+        }
+
+        if !pinned.propagate(tileset, touched) {
+            return None;
+        }
+
+        for _ in 0..MAX_GENERATION_ATTEMPTS {
+            let mut strip = pinned.clone_cells();
+            if strip.solve(tileset, &rng) {
+                return Some(strip);
+            }
+        }
+
+        None
+    }
+
+    /// Repeatedly collapse the lowest entropy cell until every cell is decided. Returns false on a contradiction.
+    fn solve(&mut self, tileset: &WaveFunctionTileset, rng: &RandGenerator) -> bool {
+        loop {
+            let mut lowest_entropy = usize::MAX;
+            let mut candidates = Vec::new();
+
+            for (index, cell) in self.cells.iter().enumerate() {
+                let entropy = cell.get_entropy();
+                if entropy == 0 {
+                    return false;
+                }
+
+                if entropy > 1 {
+                    if entropy < lowest_entropy {
+                        lowest_entropy = entropy;
+                        candidates.clear();
+                    }
+                    if entropy == lowest_entropy {
+                        candidates.push(index);
+                    }
+                }
+            }
+
+            if candidates.is_empty() {
+                return true;
+            }
+
+            let index = candidates[rng.gen_range(0, candidates.len())];
+            self.cells[index].collapse(tileset, rng);
+
+            if !self.propagate(tileset, vec![index]) {
+                return false;
+            }
+        }
+    }
+
+    /// Remove neighbouring states that can no longer sit next to the changed cells, spreading outwards until nothing changes
+    fn propagate(&mut self, tileset: &WaveFunctionTileset, mut stack: Vec<usize>) -> bool {
+        while let Some(index) = stack.pop() {
+            let (cell_x, cell_y) = ((index % self.width) as i32, (index / self.width) as i32);
+
+            for (direction, (offset_x, offset_y)) in DIRECTION_OFFSETS.iter().enumerate() {
+                let (neighbour_x, neighbour_y) = (cell_x + offset_x, cell_y + offset_y);
+                if neighbour_x < 0 || neighbour_y < 0 || neighbour_x >= self.width as i32 || neighbour_y >= self.height as i32 {
+                    continue;
+                }
+
+                // Collect the distinct edges this cell could present to the neighbour
+                let mut edges: Vec<WaveFunctionEdgeHandle> = Vec::new();
+                for state in self.cells[index].states.iter() {
+                    if let Some(edge) = tileset.get_edge(state, direction) {
+                        if !edges.contains(&edge) {
+                            edges.push(edge);
+                        }
+                    }
+                }
+
+                let neighbour_index = self.get_index(neighbour_x as usize, neighbour_y as usize);
+                let neighbour = &mut self.cells[neighbour_index];
+                let previous_entropy = neighbour.get_entropy();
+
+                neighbour.states.retain(|state| {
+                    edges.iter().any(|edge| tileset.get_valid_neighbours(direction, edge).is_some_and(|valid| valid.contains(state)))
+                });
+
+                match neighbour.get_entropy() {
+                    0 => return false,
+                    entropy if entropy != previous_entropy => stack.push(neighbour_index),
+                    _ => {}
+                }
+            }
+        }
+
+        true
+    }
+
+    #[inline]
+    fn get_index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    #[inline]
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn get_tile(&self, x: usize, y: usize) -> Option<WaveFunctionTileHandle> {
+        if x < self.width && y < self.height {
+            self.cells[self.get_index(x, y)].get_tile_data()
+        } else {
+            None
+        }
+    }
+
+    /// Overwrite a cell and mark the sector as modified. Returns false if the cell is outside the sector.
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: WaveFunctionTileHandle) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+
+        let index = self.get_index(x, y);
+        self.cells[index] = WaveFunctionCell::new_collapsed(tile);
+        self.modified = true;
+        true
+    }
+
+    fn to_tiles(&self) -> Vec<Option<WaveFunctionTileHandle>> {
+        self.cells.iter().map(|cell| cell.get_tile_data()).collect()
+    }
+
+    fn get_memory_usage(&self) -> usize {
+        size_of::<Self>() + self.cells.iter().map(|cell| cell.get_memory_usage()).sum::<usize>()
+    }
+}
+
+/// Controls which sectors `WaveFunctionField::stream_around` keeps in memory. Distances are in sectors and measured
+/// as the larger of the x and y distance, so a radius of 1 is the 3x3 block around the focus.
+#[derive(Debug, Clone, Copy)]
+pub struct WaveFunctionStreamingPolicy {
+    /// Sectors within this distance of the focus are generated or restored
+    pub load_radius: i32,
+
+    /// Sectors beyond this distance of the focus are evicted. Keeping this larger than `load_radius`
+    /// stops sectors thrashing when the focus moves back and forth over a sector boundary.
+    pub unload_radius: i32,
+}
+
+impl Default for WaveFunctionStreamingPolicy {
+    fn default() -> WaveFunctionStreamingPolicy {
+        WaveFunctionStreamingPolicy {
+            load_radius: 1,
+            unload_radius: 2,
         }
     }
 }
 
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WaveFunctionFieldStats {
+    /// Sectors currently held in full
+    pub loaded_sectors: usize,
+
+    /// Evicted sectors kept because they were modified
+    pub persisted_sectors: usize,
+
+    /// Sectors waiting on, or being solved by, the generator
+    pub pending_sectors: usize,
+
+    /// Approximate heap and inline size of the loaded sectors in bytes
+    pub loaded_bytes: usize,
+
+    /// Approximate size of the persisted sectors in bytes
+    pub persisted_bytes: usize,
+
+    /// Sectors generated from the seed since the field was created, including regenerations
+    pub generated_total: usize,
+
+    /// Sectors restored from the persisted store
+    pub restored_total: usize,
+
+    /// Sectors evicted since the field was created
+    pub evicted_total: usize,
+}
 
+struct PendingSector {
+    // The epoch the sector was requested in
+    epoch: u64,
+
+    // Shared with the generator's copy of the request
+    cancelled: Arc<AtomicBool>,
+}
 
 pub struct WaveFunctionField {
     sectors: InfiniteGrid<WaveFunctionSector>,
+    persisted: InfiniteGrid<Vec<Option<WaveFunctionTileHandle>>>,
+    pending: InfiniteGrid<PendingSector>,
     sector_width: usize,
     sector_height: usize,
 
-    tileset: WaveFunctionTileset,
+    seed: u64,
+    policy: WaveFunctionStreamingPolicy,
+    stats: WaveFunctionFieldStats,
+
+    generator: SectorGenerator,
+    tileset: Arc<WaveFunctionTileset>,
+
+    // Bumped whenever the tileset changes so sectors solved with the old one are thrown away
+    epoch: u64,
 }
 
 impl WaveFunctionField {
     pub fn new(tileset: WaveFunctionTileset) -> Self {
+        Self::new_with_seed(tileset, 0)
+    }
+
+    pub fn new_with_seed(tileset: WaveFunctionTileset, seed: u64) -> Self {
         WaveFunctionField {
             sectors: InfiniteGrid::new(),
+            persisted: InfiniteGrid::new(),
+            pending: InfiniteGrid::new(),
             sector_width: 16,
             sector_height: 16,
-            tileset,
+            seed,
+            policy: WaveFunctionStreamingPolicy::default(),
+            stats: WaveFunctionFieldStats::default(),
+            generator: SectorGenerator::new(),
+            tileset: Arc::new(tileset),
+            epoch: 0,
         }
     }
 
-    pub fn collapse_sector(&mut self, x: i32, y: i32) {
-        if let Some(sector) = self.sectors.get_mut(x, y) {
-            // Do a trivial collapse just to test some stuff!
-            for cell in sector.cells.iter_mut() {
-                cell.collapse(&self.tileset)
-            }
-        }
+    pub fn set_streaming_policy(&mut self, policy: WaveFunctionStreamingPolicy) {
+        self.policy = policy;
     }
 
     pub fn add_sector(&mut self, x: i32, y: i32) {
-        if let None = self.sectors.get(x, y) {
-            self.sectors.set(x, y, WaveFunctionSector::new(&self.tileset, self.sector_width, self.sector_height));
-        } else {
+        if self.sectors.contains(x, y) {
             // Some error condition!
             panic!("Attempting to add sector to occupied location ({:?},{:?})!", x, y);
         }
+
+        // Generating it here supersedes any request still with the generator
+        self.cancel_pending(x, y);
+
+        // Modified sectors come back exactly as the player left them, everything else is regenerated from the seed
+        let sector = if let Some(tiles) = self.persisted.remove(x, y) {
+            self.stats.restored_total += 1;
+            WaveFunctionSector::from_tiles(&self.tileset, self.sector_width, self.sector_height, &tiles)
+        } else {
+            self.stats.generated_total += 1;
+            WaveFunctionSector::generate(&self.tileset, self.seed, x, y, self.sector_width, self.sector_height)
+        };
+
+        self.sectors.set(x, y, sector);
+    }
+
+    /// Unload a sector, keeping a compact copy of it if it was modified. Returns false if the sector wasn't loaded.
+    pub fn evict_sector(&mut self, x: i32, y: i32) -> bool {
+        match self.sectors.remove(x, y) {
+            Some(sector) => {
+                if sector.is_modified() {
+                    self.persisted.set(x, y, sector.to_tiles());
+                }
+                self.stats.evicted_total += 1;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Queue a sector for generation on the worker threads. Modified sectors are restored straight away
+    /// as there is nothing to solve. Does nothing if the sector is already loaded or pending.
+    pub fn request_sector(&mut self, x: i32, y: i32) {
+        if self.sectors.contains(x, y) || self.pending.contains(x, y) {
+            return;
+        }
+
+        if self.persisted.contains(x, y) {
+            self.add_sector(x, y);
+            return;
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.pending.set(x, y, PendingSector { epoch: self.epoch, cancelled: cancelled.clone() });
+        self.generator.submit(SectorRequest {
+            x,
+            y,
+            seed: self.seed,
+            width: self.sector_width,
+            height: self.sector_height,
+            tileset: self.tileset.clone(),
+            epoch: self.epoch,
+            cancelled,
+        });
+    }
+
+    /// Forget a pending sector and tell the generator not to bother solving it
+    fn cancel_pending(&mut self, x: i32, y: i32) {
+        if let Some(pending) = self.pending.remove(x, y) {
+            pending.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Merge any sectors the worker threads have finished into the field. Returns the number of sectors added.
+    pub fn update(&mut self) -> usize {
+        let finished = self.generator.drain();
+        self.merge_generated(finished)
+    }
+
+    /// Block until every pending sector has been generated and merged
+    pub fn wait_for_pending(&mut self) -> usize {
+        let finished = self.generator.wait();
+        self.merge_generated(finished)
     }
 
-    pub fn get_cell_render_data(&self, f: &dyn Fn((&str, f32))) {
-        if let Some(sector) = self.sectors.get(0, 0) {
-            for cell in sector.cells.iter() {
+    fn merge_generated(&mut self, finished: Vec<GeneratedSector>) -> usize {
+        let mut added = 0;
+
+        for generated in finished {
+            // Sectors that were cancelled, generated synchronously or requested with an older tileset are dropped
+            if self.pending.get(generated.x, generated.y).map(|pending| pending.epoch) == Some(generated.epoch) {
+                self.pending.remove(generated.x, generated.y);
+                self.sectors.set(generated.x, generated.y, generated.sector);
+                self.stats.generated_total += 1;
+                added += 1;
+            }
+        }
+
+        added
+    }
+
+    /// Swap in a rebuilt tileset and regenerate every loaded sector from the same seed. Modified sectors keep
+    /// their tiles where the new tileset still has a tile with the same id and rotation.
+    pub fn set_tileset(&mut self, tileset: WaveFunctionTileset) {
+        let previous = std::mem::replace(&mut self.tileset, Arc::new(tileset));
+        self.epoch += 1;
+
+        // Requests made with the old tileset are cancelled and made again with the new one
+        let mut regenerate: Vec<(i32, i32)> = self.pending.coords().collect();
+        for (x, y) in regenerate.iter() {
+            self.cancel_pending(*x, *y);
+        }
+
+        let remap = |tiles: &[Option<WaveFunctionTileHandle>]| -> Vec<Option<WaveFunctionTileHandle>> {
+            tiles.iter().map(|tile| tile.and_then(|handle| self.tileset.find_equivalent_tile(&previous, &handle))).collect()
+        };
+
+        let persisted: Vec<_> = self.persisted.iter().map(|((x, y), tiles)| (x, y, remap(tiles))).collect();
+        for (x, y, tiles) in persisted {
+            self.persisted.set(x, y, tiles);
+        }
+
+        let loaded: Vec<(i32, i32)> = self.sectors.coords().collect();
+        for (x, y) in loaded {
+            if let Some(sector) = self.sectors.remove(x, y) {
+                if sector.is_modified() {
+                    let tiles = remap(&sector.to_tiles());
+                    self.sectors.set(x, y, WaveFunctionSector::from_tiles(&self.tileset, self.sector_width, self.sector_height, &tiles));
+                } else {
+                    regenerate.push((x, y));
+                }
+            }
+        }
+
+        for (x, y) in regenerate {
+            self.request_sector(x, y);
+        }
+    }
+
+    #[inline]
+    pub fn is_pending(&self, x: i32, y: i32) -> bool {
+        self.pending.contains(x, y)
+    }
+
+    /// Request every sector within the policy's load radius of the given sector, nearest first, and evict or cancel
+    /// those beyond its unload radius. Requested sectors arrive through `update`.
+    pub fn stream_around(&mut self, x: i32, y: i32) {
+        let distance = |sector_x: i32, sector_y: i32| i32::max((sector_x - x).abs(), (sector_y - y).abs());
+
+        let evicted: Vec<(i32, i32)> = self.sectors.coords()
+            .filter(|(sector_x, sector_y)| distance(*sector_x, *sector_y) > self.policy.unload_radius)
+            .collect();
+
+        for (sector_x, sector_y) in evicted {
+            self.evict_sector(sector_x, sector_y);
+        }
+
+        let cancelled: Vec<(i32, i32)> = self.pending.coords()
+            .filter(|(sector_x, sector_y)| distance(*sector_x, *sector_y) > self.policy.unload_radius)
+            .collect();
+
+        for (sector_x, sector_y) in cancelled {
+            self.cancel_pending(sector_x, sector_y);
+        }
+
+        let radius = self.policy.load_radius;
+        let mut requested = Vec::new();
+        for sector_y in y - radius..=y + radius {
+            for sector_x in x - radius..=x + radius {
+                requested.push((sector_x, sector_y));
+            }
+        }
+        requested.sort_by_key(|(sector_x, sector_y)| distance(*sector_x, *sector_y));
+
+        for (sector_x, sector_y) in requested {
+            self.request_sector(sector_x, sector_y);
+        }
+    }
+
+    /// Convert a cell position in field space to the sector containing it and the cell offset within that sector
+    pub fn get_sector_coords(&self, cell_x: i32, cell_y: i32) -> ((i32, i32), (usize, usize)) {
+        let (width, height) = (self.sector_width as i32, self.sector_height as i32);
+
+        (
+            (cell_x.div_euclid(width), cell_y.div_euclid(height)),
+            (cell_x.rem_euclid(width) as usize, cell_y.rem_euclid(height) as usize)
+        )
+    }
+
+    pub fn get_cell(&self, cell_x: i32, cell_y: i32) -> Option<WaveFunctionTileHandle> {
+        let ((sector_x, sector_y), (x, y)) = self.get_sector_coords(cell_x, cell_y);
+        self.sectors.get(sector_x, sector_y).and_then(|sector| sector.get_tile(x, y))
+    }
+
+    /// Look up a gameplay property of the tile in a loaded cell
+    pub fn get_cell_property(&self, cell_x: i32, cell_y: i32, key: &str) -> Option<&TileProperty> {
+        self.get_cell(cell_x, cell_y).and_then(|tile| self.tileset.get_property(&tile, key))
+    }
+
+    /// Overwrite a cell in a loaded sector, marking the sector as modified so it survives eviction.
+    /// Returns false if the sector isn't loaded.
+    pub fn set_cell(&mut self, cell_x: i32, cell_y: i32, tile: WaveFunctionTileHandle) -> bool {
+        let ((sector_x, sector_y), (x, y)) = self.get_sector_coords(cell_x, cell_y);
+        match self.sectors.get_mut(sector_x, sector_y) {
+            Some(sector) => sector.set_tile(x, y, tile),
+            None => false
+        }
+    }
+
+    #[inline]
+    pub fn has_sector(&self, x: i32, y: i32) -> bool {
+        self.sectors.contains(x, y)
+    }
+
+    pub fn get_sector(&self, x: i32, y: i32) -> Option<&WaveFunctionSector> {
+        self.sectors.get(x, y)
+    }
+
+    pub fn get_loaded_sectors(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.sectors.coords()
+    }
+
+    #[inline]
+    pub fn get_sector_width(&self) -> usize {
+        self.sector_width
+    }
+
+    #[inline]
+    pub fn get_sector_height(&self) -> usize {
+        self.sector_height
+    }
+
+    #[inline]
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    pub fn get_tileset(&self) -> &WaveFunctionTileset {
+        &self.tileset
+    }
+
+    pub fn get_stats(&self) -> WaveFunctionFieldStats {
+        let persisted_bytes = self.persisted.iter()
+            .map(|(_, tiles)| size_of::<Vec<Option<WaveFunctionTileHandle>>>() + tiles.capacity() * size_of::<Option<WaveFunctionTileHandle>>())
+            .sum();
+
+        WaveFunctionFieldStats {
+            loaded_sectors: self.sectors.len(),
+            persisted_sectors: self.persisted.len(),
+            pending_sectors: self.pending.len(),
+            loaded_bytes: self.sectors.iter().map(|(_, sector)| sector.get_memory_usage()).sum(),
+            persisted_bytes,
+            ..self.stats
+        }
+    }
+
+    /// Visit the render data of every collapsed cell in a sector along with its cell offset within the sector
+    pub fn get_sector_render_data<F>(&self, x: i32, y: i32, mut f: F)
+    where
+        F: FnMut(usize, usize, (&str, f32))
+    {
+        if let Some(sector) = self.sectors.get(x, y) {
+            for (index, cell) in sector.cells.iter().enumerate() {
                 if let Some(data) = cell.get_tile_data() {
                     if let Some(render_data) = self.tileset.get_render_data(&data) {
-                        f(render_data);
+                        f(index % sector.width, index / sector.width, render_data);
                     }
                 }
             }
         }
     }
-}
 
+    /// Like `get_sector_render_data`, but hands out texture handles instead of texture ids
+    pub fn get_sector_texture_data<F>(&self, x: i32, y: i32, mut f: F)
+    where
+        F: FnMut(usize, usize, (WaveFunctionTextureHandle, f32))
+    {
+        if let Some(sector) = self.sectors.get(x, y) {
+            for (index, cell) in sector.cells.iter().enumerate() {
+                if let Some(data) = cell.get_tile_data() {
+                    if let Some(texture_data) = self.tileset.get_texture_data(&data) {
+                        f(index % sector.width, index / sector.width, texture_data);
+                    }
+                }
+            }
+        }
+    }
+}
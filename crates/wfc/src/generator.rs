@@ -0,0 +1,177 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{field::WaveFunctionSector, tileset::WaveFunctionTileset};
+
+pub(crate) struct SectorRequest {
+    pub x: i32,
+    pub y: i32,
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub tileset: Arc<WaveFunctionTileset>,
+
+    // Lets the field tell results for the current tileset apart from ones requested before a reload
+    pub epoch: u64,
+
+    // Set by the field once the sector is no longer wanted, so it can be skipped instead of solved
+    pub cancelled: Arc<AtomicBool>,
+}
+
+pub(crate) struct GeneratedSector {
+    pub x: i32,
+    pub y: i32,
+    pub epoch: u64,
+    pub sector: WaveFunctionSector,
+}
+
+impl SectorRequest {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Solve the sector, or `None` if it was cancelled while it waited
+    fn generate(self) -> Option<GeneratedSector> {
+        if self.is_cancelled() {
+            return None;
+        }
+
+        let sector = WaveFunctionSector::generate(&self.tileset, self.seed, self.x, self.y, self.width, self.height);
+        Some(GeneratedSector { x: self.x, y: self.y, epoch: self.epoch, sector })
+    }
+}
+
+/// Generates sectors on a pool of worker threads. Every sector's border is derived from the seed up front,
+/// so any set of pending sectors can be solved at the same time without waiting on their neighbours.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct SectorGenerator {
+    requests: Option<std::sync::mpsc::Sender<SectorRequest>>,
+    // Cancelled requests come back as `None` so they still count against `in_flight`
+    results: std::sync::mpsc::Receiver<Option<GeneratedSector>>,
+    workers: Vec<std::thread::JoinHandle<()>>,
+    in_flight: usize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SectorGenerator {
+    pub fn new() -> Self {
+        use std::sync::{mpsc::channel, Mutex};
+
+        let (request_sender, request_receiver) = channel::<SectorRequest>();
+        let (result_sender, result_receiver) = channel();
+        let request_receiver = Arc::new(Mutex::new(request_receiver));
+
+        // Leave a core for the main thread
+        let worker_count = std::thread::available_parallelism().map_or(1, |count| count.get().saturating_sub(1).max(1));
+
+        let workers = (0..worker_count).map(|index| {
+            let requests = request_receiver.clone();
+            let results = result_sender.clone();
+
+            std::thread::Builder::new()
+                .name(format!("wfc-sector-{}", index))
+                .spawn(move || loop {
+                    // Only hold the lock while waiting for a request, not while generating
+                    let request = match requests.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => break,
+                    };
+
+                    match request {
+                        Ok(request) => {
+                            if results.send(request.generate()).is_err() {
+                                break;
+                            }
+                        },
+                        Err(_) => break,
+                    }
+                })
+                .expect("Unable to spawn sector generation thread")
+        }).collect();
+
+        SectorGenerator {
+            requests: Some(request_sender),
+            results: result_receiver,
+            workers,
+            in_flight: 0,
+        }
+    }
+
+    pub fn submit(&mut self, request: SectorRequest) {
+        if let Some(sender) = self.requests.as_ref() {
+            if sender.send(request).is_ok() {
+                self.in_flight += 1;
+            }
+        }
+    }
+
+    /// Collect every sector that has finished generating without blocking
+    pub fn drain(&mut self) -> Vec<GeneratedSector> {
+        let finished: Vec<_> = self.results.try_iter().collect();
+        self.in_flight -= finished.len();
+        finished.into_iter().flatten().collect()
+    }
+
+    /// Block until every submitted sector has finished generating
+    pub fn wait(&mut self) -> Vec<GeneratedSector> {
+        let mut finished = Vec::with_capacity(self.in_flight);
+        while self.in_flight > 0 {
+            match self.results.recv() {
+                Ok(result) => {
+                    finished.extend(result);
+                    self.in_flight -= 1;
+                },
+                Err(_) => break,
+            }
+        }
+        finished
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for SectorGenerator {
+    fn drop(&mut self) {
+        // Closing the request channel lets the workers fall out of their loops
+        self.requests = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+// Sectors solved per `drain` on wasm32, so streaming in a new area is spread over several frames
+#[cfg(target_arch = "wasm32")]
+const MAX_SECTORS_PER_DRAIN: usize = 2;
+
+/// Without threads on wasm32 the pending sectors are generated on the main thread when they are collected,
+/// a few at a time in the order they were submitted
+#[cfg(target_arch = "wasm32")]
+pub(crate) struct SectorGenerator {
+    pending: Vec<SectorRequest>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl SectorGenerator {
+    pub fn new() -> Self {
+        SectorGenerator {
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn submit(&mut self, request: SectorRequest) {
+        self.pending.push(request);
+    }
+
+    pub fn drain(&mut self) -> Vec<GeneratedSector> {
+        self.pending.retain(|request| !request.is_cancelled());
+
+        let count = self.pending.len().min(MAX_SECTORS_PER_DRAIN);
+        self.pending.drain(..count).filter_map(SectorRequest::generate).collect()
+    }
+
+    pub fn wait(&mut self) -> Vec<GeneratedSector> {
+        self.pending.drain(..).filter_map(SectorRequest::generate).collect()
+    }
+}
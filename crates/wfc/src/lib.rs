@@ -1,4 +1,8 @@
-
+// The code generated by nanoserde's DeJson derive for optional fields trips this lint
+#![allow(clippy::question_mark)]
 
 pub mod tileset;
-pub mod field;
\ No newline at end of file
+pub mod field;
+pub mod property;
+pub mod watcher;
+mod generator;
\ No newline at end of file
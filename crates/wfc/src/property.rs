@@ -0,0 +1,64 @@
+use std::{collections::HashMap, str::Chars};
+
+use nanoserde::{DeJson, DeJsonErr, DeJsonState, DeJsonTok};
+
+/// A gameplay value attached to a tile in the tileset JSON, e.g. `"walkable": true` or `"spawn_tags": ["npc"]`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TileProperty {
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<TileProperty>),
+    Table(HashMap<String, TileProperty>),
+}
+
+impl TileProperty {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            TileProperty::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            TileProperty::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            TileProperty::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[TileProperty]> {
+        match self {
+            TileProperty::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&HashMap<String, TileProperty>> {
+        match self {
+            TileProperty::Table(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+// The derive only handles externally tagged enums, so pick the variant from the JSON token instead
+impl DeJson for TileProperty {
+    fn de_json(state: &mut DeJsonState, input: &mut Chars) -> Result<Self, DeJsonErr> {
+        match state.tok {
+            DeJsonTok::Bool(_) => Ok(TileProperty::Bool(bool::de_json(state, input)?)),
+            DeJsonTok::U64(_) | DeJsonTok::I64(_) | DeJsonTok::F64(_) => Ok(TileProperty::Number(f64::de_json(state, input)?)),
+            DeJsonTok::Str => Ok(TileProperty::String(String::de_json(state, input)?)),
+            DeJsonTok::BlockOpen => Ok(TileProperty::List(Vec::de_json(state, input)?)),
+            DeJsonTok::CurlyOpen => Ok(TileProperty::Table(HashMap::de_json(state, input)?)),
+            _ => Err(state.err_token("bool, number, string, list or table")),
+        }
+    }
+}
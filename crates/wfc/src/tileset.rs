@@ -1,14 +1,17 @@
-use std::{collections::{HashMap, HashSet}, hash::Hash};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use macroquad::prelude::load_string;
 use nanoserde::DeJson;
 
+use crate::property::TileProperty;
+
 #[derive(DeJson)]
 struct TileData {
     id: String,
     texture_id: String,
     edge_ids: Vec<String>,
     can_rotate: bool,
+    properties: Option<HashMap<String, TileProperty>>,
 }
 
 #[derive(DeJson)]
@@ -36,6 +39,60 @@ impl TilesetData {
             Err(format!("Unable to load TilesetData at path: {}", data_path))
         }
     }
+
+    pub fn from_json(contents: &str) -> Result<Self, String> {
+        TilesetData::deserialize_json(contents).map_err(|err| format!("Unable to parse input as TilesetData: {}", err))
+    }
+
+    /// Check the data for mistakes that would otherwise be silently ignored or panic when building the tileset.
+    /// Returns a readable description of each problem found.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.tiles.is_empty() {
+            errors.push("Tileset has no tiles".to_string());
+        }
+
+        for tile in self.tiles.iter() {
+            if tile.edge_ids.len() != 4 {
+                errors.push(format!("Tile {} has {} edges, expected 4", tile.id, tile.edge_ids.len()));
+            }
+
+            if !self.weights.iter().any(|weight| weight.0 == tile.id) {
+                errors.push(format!("Tile {} has no weight and will never be picked", tile.id));
+            }
+        }
+
+        let is_known_edge = |edge: &String| self.tiles.iter().any(|tile| tile.edge_ids.contains(edge));
+
+        for rule in self.rules.iter() {
+            for edge in [&rule.0, &rule.1] {
+                if !is_known_edge(edge) {
+                    errors.push(format!("Rule [{}, {}] refers to unknown edge {}", rule.0, rule.1, edge));
+                }
+            }
+        }
+
+        let mut unruled_edges: Vec<&String> = Vec::new();
+        for edge in self.tiles.iter().flat_map(|tile| tile.edge_ids.iter()) {
+            if !unruled_edges.contains(&edge) && !self.rules.iter().any(|rule| rule.0 == *edge || rule.1 == *edge) {
+                unruled_edges.push(edge);
+                errors.push(format!("Edge {} isn't in any rule, tiles using it can never be placed", edge));
+            }
+        }
+
+        for weight in self.weights.iter() {
+            if !self.tiles.iter().any(|tile| tile.id == weight.0) {
+                errors.push(format!("Weight refers to unknown tile {}", weight.0));
+            }
+
+            if weight.1 < 0.0 || !weight.1.is_finite() {
+                errors.push(format!("Tile {} has an invalid weight of {}", weight.0, weight.1));
+            }
+        }
+
+        errors
+    }
 }
 
 pub const DIRECTION_UP: usize = 0;
@@ -46,7 +103,7 @@ pub const DIRECTION_LEFT: usize = 3;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct WaveFunctionTileClassHandle(usize);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct WaveFunctionTileHandle(usize);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -55,6 +112,13 @@ pub struct WaveFunctionEdgeHandle(usize);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct WaveFunctionTextureHandle(usize);
 
+impl WaveFunctionTextureHandle {
+    /// Position of the texture in `WaveFunctionTileset::get_texture_ids`
+    pub fn get_index(&self) -> usize {
+        self.0
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct WaveFunctionRule(WaveFunctionEdgeHandle, WaveFunctionEdgeHandle);
 
@@ -84,7 +148,10 @@ pub struct WaveFunctionTileset {
     rules: Vec<WaveFunctionRule>,
     weights: Vec<WaveFunctionWeight>,
 
-    high_entropy_cache: HashSet<WaveFunctionTileHandle>,
+    // Gameplay properties shared by every rotation of a class, indexed by class handle
+    class_properties: Vec<HashMap<String, TileProperty>>,
+
+    high_entropy_cache: BTreeSet<WaveFunctionTileHandle>,
     validity_cache: [HashMap<WaveFunctionEdgeHandle, HashSet<WaveFunctionTileHandle>>; 4],
 }
 
@@ -98,7 +165,8 @@ impl WaveFunctionTileset {
             tiles: Vec::new(),
             rules: Vec::new(),
             weights: Vec::new(),
-            high_entropy_cache: HashSet::new(),
+            class_properties: Vec::new(),
+            high_entropy_cache: BTreeSet::new(),
             validity_cache: [
                 HashMap::new(),
                 HashMap::new(),
@@ -116,9 +184,14 @@ impl WaveFunctionTileset {
             } else {
                 let next_index = tileset.tile_id_map.len();
                 tileset.tile_id_map.push(tile_data.id.clone());
+                tileset.class_properties.push(HashMap::new());
                 next_index
             };
 
+            if let Some(properties) = tile_data.properties {
+                tileset.class_properties[tile_id].extend(properties);
+            }
+
             // Get or add the current texture ID
             let texture_id = if let Some(found) = tileset.texture_id_map.iter().position(|id| *id == tile_data.texture_id) {
                 found
@@ -130,7 +203,7 @@ impl WaveFunctionTileset {
 
             // Process the edge ids for the tile
             let mut edges: [WaveFunctionEdgeHandle; 4] = [WaveFunctionEdgeHandle(0); 4];
-            for (index, edge_id) in tile_data.edge_ids.iter().enumerate() {
+            for (index, edge_id) in tile_data.edge_ids.iter().take(4).enumerate() {
                 let edge_index = if let Some(found) = tileset.edge_id_map.iter().position(|id| id == edge_id) {
                     found
                 } else {
@@ -151,7 +224,7 @@ impl WaveFunctionTileset {
 
             if tile_data.can_rotate {
                 for r in 1..4 {
-                    let mut tile = wf_tile.clone();
+                    let mut tile = wf_tile;
                     // Perform the rotation by shifting the edges to the right
                     // This is due to the fact that the edge representation processes clockwise starting with the top edge
                     tile.edge_ids.rotate_right(r);
@@ -271,26 +344,109 @@ impl WaveFunctionTileset {
         tileset
     }
 
-    pub fn get_edge_handle(&self, edge: &String) -> Option<WaveFunctionEdgeHandle> {
-        if let Some(found) = self.edge_id_map.iter().position(|id| id == edge) {
-            Some(WaveFunctionEdgeHandle(found))
+    /// Parse and validate a tileset, returning every problem found rather than building a broken one
+    pub fn from_json(contents: &str) -> Result<Self, Vec<String>> {
+        let data = TilesetData::from_json(contents).map_err(|err| vec![err])?;
+
+        let errors = data.validate();
+        if errors.is_empty() {
+            Ok(WaveFunctionTileset::new(data))
         } else {
-            None
+            Err(errors)
         }
     }
 
-    pub fn get_tile_class_handle(&self, tile_id: &String) -> Option<WaveFunctionTileClassHandle> {
-        if let Some(found) = self.tile_id_map.iter().position(|id| id == tile_id) {
-            Some(WaveFunctionTileClassHandle(found))
-        } else {
-            None
-        }
+    pub async fn from_file(path: &str) -> Result<Self, Vec<String>> {
+        let contents = load_string(path).await.map_err(|_| vec![format!("Unable to load TilesetData at path: {}", path)])?;
+        Self::from_json(&contents)
+    }
+
+    pub fn get_edge_handle(&self, edge: &str) -> Option<WaveFunctionEdgeHandle> {
+        self.edge_id_map.iter().position(|id| id == edge).map(WaveFunctionEdgeHandle)
+    }
+
+    pub fn get_tile_class_handle(&self, tile_id: &str) -> Option<WaveFunctionTileClassHandle> {
+        self.tile_id_map.iter().position(|id| id == tile_id).map(WaveFunctionTileClassHandle)
     }
 
-    pub fn get_high_entropy_cache_clone(&self) -> HashSet<WaveFunctionTileHandle> {
+    pub fn get_high_entropy_cache_clone(&self) -> BTreeSet<WaveFunctionTileHandle> {
         self.high_entropy_cache.clone()
     }
 
+    /// Every tile handle in the tileset, including the rotated permutations
+    pub fn get_tile_handles(&self) -> impl Iterator<Item = WaveFunctionTileHandle> + '_ {
+        self.high_entropy_cache.iter().cloned()
+    }
+
+    #[inline]
+    pub fn get_tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn get_edge(&self, handle: &WaveFunctionTileHandle, direction: usize) -> Option<WaveFunctionEdgeHandle> {
+        self.tiles.get(handle.0).map(|tile| tile.edge_ids[direction])
+    }
+
+    /// The tiles which may sit next to an edge when looking out from it in the given direction
+    pub fn get_valid_neighbours(&self, direction: usize, edge: &WaveFunctionEdgeHandle) -> Option<&HashSet<WaveFunctionTileHandle>> {
+        self.validity_cache[direction].get(edge)
+    }
+
+    pub fn is_edge_compatible(&self, a: &WaveFunctionEdgeHandle, b: &WaveFunctionEdgeHandle) -> bool {
+        self.rules.iter().any(|rule| (rule.0 == *a && rule.1 == *b) || (rule.0 == *b && rule.1 == *a))
+    }
+
+    /// The weight of a single tile, which is its class's weight
+    pub fn get_tile_weight(&self, handle: &WaveFunctionTileHandle) -> f32 {
+        match self.get_class_from_tile(handle) {
+            Some(class) => self.get_weight(&class),
+            None => 0.0,
+        }
+    }
+
+    /// Look up a gameplay property of a tile. Properties belong to the tile as written in the tileset,
+    /// so anything directional (a collision shape, say) is unrotated and should be turned by `get_rotation`.
+    pub fn get_property(&self, handle: &WaveFunctionTileHandle, key: &str) -> Option<&TileProperty> {
+        self.get_class_from_tile(handle).and_then(|class| self.class_properties[class.0].get(key))
+    }
+
+    pub fn get_properties(&self, handle: &WaveFunctionTileHandle) -> Option<&HashMap<String, TileProperty>> {
+        self.get_class_from_tile(handle).map(|class| &self.class_properties[class.0])
+    }
+
+    pub fn get_bool_property(&self, handle: &WaveFunctionTileHandle, key: &str) -> Option<bool> {
+        self.get_property(handle, key).and_then(TileProperty::as_bool)
+    }
+
+    pub fn get_number_property(&self, handle: &WaveFunctionTileHandle, key: &str) -> Option<f64> {
+        self.get_property(handle, key).and_then(TileProperty::as_number)
+    }
+
+    pub fn get_string_property(&self, handle: &WaveFunctionTileHandle, key: &str) -> Option<&str> {
+        self.get_property(handle, key).and_then(TileProperty::as_str)
+    }
+
+    /// The id of the tile as written in the tileset, shared by all of its rotations
+    pub fn get_tile_id(&self, handle: &WaveFunctionTileHandle) -> Option<&str> {
+        self.tiles.get(handle.0).map(|tile| self.tile_id_map[tile.class_id.0].as_str())
+    }
+
+    pub fn find_tile(&self, tile_id: &str, rotation: u32) -> Option<WaveFunctionTileHandle> {
+        let class_handle = self.get_tile_class_handle(tile_id)?;
+        self.tiles.iter()
+            .position(|tile| tile.class_id == class_handle && tile.rotation == rotation)
+            .map(WaveFunctionTileHandle)
+    }
+
+    /// Translate a handle from another tileset, usually an older version of this one, by tile id and rotation
+    pub fn find_equivalent_tile(&self, other: &WaveFunctionTileset, handle: &WaveFunctionTileHandle) -> Option<WaveFunctionTileHandle> {
+        self.find_tile(other.get_tile_id(handle)?, other.get_rotation(handle)?)
+    }
+
+    pub fn get_rotation(&self, handle: &WaveFunctionTileHandle) -> Option<u32> {
+        self.tiles.get(handle.0).map(|tile| tile.rotation)
+    }
+
     pub fn get_weight(&self, class_handle: &WaveFunctionTileClassHandle) -> f32 {
         match self.weights.iter().find(|&weight| weight.0 == *class_handle) {
             Some(found) => found.1,
@@ -299,18 +455,21 @@ impl WaveFunctionTileset {
     }
 
     pub fn get_class_from_tile(&self, handle: &WaveFunctionTileHandle) -> Option<WaveFunctionTileClassHandle> {
-        match self.tiles.get(handle.0) {
-            Some(tile) => Some(tile.class_id),
-            None => None,
-        }
+        self.tiles.get(handle.0).map(|tile| tile.class_id)
     }
 
     pub fn get_render_data(&self, handle: &WaveFunctionTileHandle) -> Option<(&str, f32)> {
-        if let Some(data) = self.tiles.get(handle.0) {
-            Some((&self.texture_id_map[data.texture_id.0], data.rotation as f32 * 90.0))
-        } else {
-            None
-        }
+        self.tiles.get(handle.0).map(|data| (self.texture_id_map[data.texture_id.0].as_str(), data.rotation as f32 * 90.0))
+    }
+
+    /// Same as `get_render_data`, with the texture as a handle so callers can keep their own per-texture data
+    pub fn get_texture_data(&self, handle: &WaveFunctionTileHandle) -> Option<(WaveFunctionTextureHandle, f32)> {
+        self.tiles.get(handle.0).map(|data| (data.texture_id, data.rotation as f32 * 90.0))
+    }
+
+    /// Every texture the tiles refer to, each listed once, in handle index order
+    pub fn get_texture_ids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.texture_id_map.iter().map(String::as_str)
     }
 
 }
\ No newline at end of file
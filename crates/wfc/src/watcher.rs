@@ -0,0 +1,69 @@
+use crate::tileset::WaveFunctionTileset;
+
+/// Watches a tileset file on disk and rebuilds the tileset whenever it changes. Files can't be watched on wasm32,
+/// so there the watcher never reports a change.
+pub struct TilesetWatcher {
+    path: String,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    modified: Option<std::time::SystemTime>,
+    #[cfg(not(target_arch = "wasm32"))]
+    last_check: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+impl TilesetWatcher {
+    pub fn new(path: &str) -> Self {
+        TilesetWatcher {
+            path: path.to_owned(),
+
+            #[cfg(not(target_arch = "wasm32"))]
+            modified: Self::get_modified(path),
+            #[cfg(not(target_arch = "wasm32"))]
+            last_check: std::time::Instant::now(),
+        }
+    }
+
+    #[inline]
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    /// Check the file for changes, at most every half second. When it has changed, returns either the rebuilt
+    /// tileset or every problem found while loading and validating it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn poll(&mut self) -> Option<Result<WaveFunctionTileset, Vec<String>>> {
+        if self.last_check.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_check = std::time::Instant::now();
+
+        let modified = Self::get_modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        Some(self.load())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn poll(&mut self) -> Option<Result<WaveFunctionTileset, Vec<String>>> {
+        None
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load(&self) -> Result<WaveFunctionTileset, Vec<String>> {
+        let contents = std::fs::read_to_string(&self.path)
+            .map_err(|err| vec![format!("Unable to load TilesetData at path {}: {}", self.path, err)])?;
+
+        WaveFunctionTileset::from_json(&contents)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_modified(path: &str) -> Option<std::time::SystemTime> {
+        std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
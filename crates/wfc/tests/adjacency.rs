@@ -0,0 +1,96 @@
+mod common;
+
+use common::{find_rule_violations, is_solved, load_tileset, random_tileset_json, tileset_from_json};
+use wfc::{
+    field::{WaveFunctionField, WaveFunctionSector},
+    tileset::{DIRECTION_DOWN, DIRECTION_LEFT, DIRECTION_RIGHT, DIRECTION_UP}
+};
+
+#[test]
+fn simple_area_sectors_satisfy_rules() {
+    let tileset = load_tileset("simple_area.json");
+
+    for seed in 0..50 {
+        for (x, y) in [(0, 0), (-3, 7), (12, -5)] {
+            let sector = WaveFunctionSector::generate(&tileset, seed, x, y, 16, 16);
+
+            assert!(is_solved(&sector), "seed {} sector ({}, {}) left cells unsolved", seed, x, y);
+            assert_eq!(find_rule_violations(&tileset, &sector), vec![], "seed {} sector ({}, {})", seed, x, y);
+        }
+    }
+}
+
+#[test]
+fn random_tileset_sectors_satisfy_rules() {
+    let mut solved = 0;
+
+    for tileset_seed in 0..40 {
+        let json = random_tileset_json(tileset_seed);
+        let tileset = tileset_from_json(&json);
+
+        for seed in 0..10 {
+            let sector = WaveFunctionSector::generate(&tileset, seed, 0, 0, 8, 8);
+
+            // Random tilesets can be unsolvable, but whatever was solved has to follow the rules
+            if is_solved(&sector) {
+                solved += 1;
+                assert_eq!(find_rule_violations(&tileset, &sector), vec![], "tileset {} seed {}: {}", tileset_seed, seed, json);
+            }
+        }
+    }
+
+    assert!(solved > 0, "No random tileset produced a solved sector");
+}
+
+#[test]
+fn seams_between_sectors_satisfy_rules() {
+    let tileset = load_tileset("simple_area.json");
+
+    for seed in 0..20 {
+        let mut field = WaveFunctionField::new_with_seed(load_tileset("simple_area.json"), seed);
+        for y in -1..=1 {
+            for x in -1..=1 {
+                field.add_sector(x, y);
+            }
+        }
+
+        for cell_y in -16..32 {
+            for cell_x in -16..32 {
+                let tile = field.get_cell(cell_x, cell_y).unwrap();
+
+                let neighbours = [
+                    (cell_x + 1, cell_y, DIRECTION_RIGHT, DIRECTION_LEFT),
+                    (cell_x, cell_y + 1, DIRECTION_DOWN, DIRECTION_UP),
+                ];
+
+                for (neighbour_x, neighbour_y, direction, opposite) in neighbours {
+                    if let Some(neighbour) = field.get_cell(neighbour_x, neighbour_y) {
+                        let edge = tileset.get_edge(&tile, direction).unwrap();
+                        let neighbour_edge = tileset.get_edge(&neighbour, opposite).unwrap();
+
+                        assert!(
+                            tileset.is_edge_compatible(&edge, &neighbour_edge),
+                            "seed {} cells ({}, {}) and ({}, {}) don't match", seed, cell_x, cell_y, neighbour_x, neighbour_y
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn generation_is_deterministic() {
+    let tileset = load_tileset("simple_area.json");
+
+    for seed in 0..10 {
+        let first = WaveFunctionSector::generate(&tileset, seed, 4, -2, 16, 16);
+        let second = WaveFunctionSector::generate(&tileset, seed, 4, -2, 16, 16);
+
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(first.get_tile(x, y), second.get_tile(x, y));
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+#![allow(dead_code)]
+
+use macroquad::rand::RandGenerator;
+use wfc::{
+    field::WaveFunctionSector,
+    tileset::{TilesetData, WaveFunctionTileset, DIRECTION_DOWN, DIRECTION_LEFT, DIRECTION_RIGHT, DIRECTION_UP}
+};
+
+pub fn tileset_from_json(contents: &str) -> WaveFunctionTileset {
+    WaveFunctionTileset::new(TilesetData::from_json(contents).unwrap())
+}
+
+pub fn load_tileset(name: &str) -> WaveFunctionTileset {
+    let path = format!("{}/../../assets/arcade_tiles/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let contents = std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("Unable to read {}: {}", path, err));
+    tileset_from_json(&contents)
+}
+
+pub fn seeded_rng(seed: u64) -> RandGenerator {
+    let rng = RandGenerator::new();
+    rng.srand(seed);
+    rng
+}
+
+/// Build a random but well formed tileset. Edge "e0" always matches itself and there is always a tile made only of it,
+/// so most tilesets are solvable, while the other edges, tiles and rules vary with the seed.
+pub fn random_tileset_json(seed: u64) -> String {
+    let rng = seeded_rng(seed);
+
+    let edge_count = rng.gen_range(2, 5);
+    let tile_count = rng.gen_range(2, 7);
+
+    let mut tiles = vec![r#"{"id": "filler", "texture_id": "filler.png", "edge_ids": ["e0", "e0", "e0", "e0"], "can_rotate": false}"#.to_string()];
+    let mut weights = vec![format!(r#"["filler", {}]"#, rng.gen_range(0.5, 5.0))];
+
+    for tile in 0..tile_count {
+        let edges: Vec<String> = (0..4).map(|_| format!(r#""e{}""#, rng.gen_range(0, edge_count))).collect();
+        let can_rotate = rng.gen_range(0, 2) == 1;
+
+        tiles.push(format!(
+            r#"{{"id": "t{}", "texture_id": "t{}.png", "edge_ids": [{}], "can_rotate": {}}}"#,
+            tile, tile, edges.join(", "), can_rotate
+        ));
+        weights.push(format!(r#"["t{}", {}]"#, tile, rng.gen_range(0.5, 5.0)));
+    }
+
+    let mut rules = vec![r#"["e0", "e0"]"#.to_string()];
+    for a in 0..edge_count {
+        for b in a..edge_count {
+            if rng.gen_range(0, 3) == 0 {
+                rules.push(format!(r#"["e{}", "e{}"]"#, a, b));
+            }
+        }
+    }
+
+    format!(
+        r#"{{"tiles": [{}], "rules": [{}], "weights": [{}]}}"#,
+        tiles.join(", "), rules.join(", "), weights.join(", ")
+    )
+}
+
+pub fn is_solved(sector: &WaveFunctionSector) -> bool {
+    (0..sector.get_height()).all(|y| (0..sector.get_width()).all(|x| sector.get_tile(x, y).is_some()))
+}
+
+/// Every horizontally and vertically adjacent pair of cells that breaks the tileset's rules
+pub fn find_rule_violations(tileset: &WaveFunctionTileset, sector: &WaveFunctionSector) -> Vec<(usize, usize, usize)> {
+    let mut violations = Vec::new();
+
+    for y in 0..sector.get_height() {
+        for x in 0..sector.get_width() {
+            let Some(tile) = sector.get_tile(x, y) else { continue };
+
+            let neighbours = [
+                (x + 1, y, DIRECTION_RIGHT, DIRECTION_LEFT),
+                (x, y + 1, DIRECTION_DOWN, DIRECTION_UP),
+            ];
+
+            for (neighbour_x, neighbour_y, direction, opposite) in neighbours {
+                if let Some(neighbour) = sector.get_tile(neighbour_x, neighbour_y) {
+                    let edge = tileset.get_edge(&tile, direction).unwrap();
+                    let neighbour_edge = tileset.get_edge(&neighbour, opposite).unwrap();
+
+                    if !tileset.is_edge_compatible(&edge, &neighbour_edge) {
+                        violations.push((x, y, direction));
+                    }
+                }
+            }
+        }
+    }
+
+    violations
+}
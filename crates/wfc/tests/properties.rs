@@ -0,0 +1,86 @@
+mod common;
+
+use common::{load_tileset, tileset_from_json};
+use wfc::{field::WaveFunctionField, property::TileProperty};
+
+const PROPERTY_TILESET: &str = r#"{
+    "tiles": [
+        {
+            "id": "Wall",
+            "texture_id": "wall.png",
+            "edge_ids": ["a", "a", "a", "a"],
+            "can_rotate": true,
+            "properties": {
+                "walkable": false,
+                "light": 0.5,
+                "footstep": "stone",
+                "spawn_tags": ["torch", "bat"],
+                "collision": {"x": 0, "y": 0, "w": 32, "h": 16}
+            }
+        },
+        {"id": "Floor", "texture_id": "floor.png", "edge_ids": ["a", "a", "a", "a"], "can_rotate": false}
+    ],
+    "rules": [["a", "a"]],
+    "weights": [["Wall", 1], ["Floor", 1]]
+}"#;
+
+#[test]
+fn properties_are_typed() {
+    let tileset = tileset_from_json(PROPERTY_TILESET);
+    let class = tileset.get_tile_class_handle("Wall").unwrap();
+    let wall = tileset.get_tile_handles().find(|handle| tileset.get_class_from_tile(handle) == Some(class)).unwrap();
+
+    assert_eq!(tileset.get_bool_property(&wall, "walkable"), Some(false));
+    assert_eq!(tileset.get_number_property(&wall, "light"), Some(0.5));
+    assert_eq!(tileset.get_string_property(&wall, "footstep"), Some("stone"));
+
+    let tags: Vec<&str> = tileset.get_property(&wall, "spawn_tags").unwrap()
+        .as_list().unwrap()
+        .iter().filter_map(TileProperty::as_str)
+        .collect();
+    assert_eq!(tags, ["torch", "bat"]);
+
+    let collision = tileset.get_property(&wall, "collision").unwrap().as_table().unwrap();
+    assert_eq!(collision.get("w"), Some(&TileProperty::Number(32.0)));
+
+    // Asking for the wrong type or a missing key gives nothing rather than a default
+    assert_eq!(tileset.get_number_property(&wall, "walkable"), None);
+    assert_eq!(tileset.get_property(&wall, "missing"), None);
+}
+
+#[test]
+fn rotations_share_properties() {
+    let tileset = tileset_from_json(PROPERTY_TILESET);
+    let class = tileset.get_tile_class_handle("Wall").unwrap();
+
+    let rotations: Vec<_> = tileset.get_tile_handles().filter(|handle| tileset.get_class_from_tile(handle) == Some(class)).collect();
+    assert_eq!(rotations.len(), 4);
+
+    for handle in rotations {
+        assert_eq!(tileset.get_string_property(&handle, "footstep"), Some("stone"));
+    }
+}
+
+#[test]
+fn tiles_without_properties_have_none() {
+    let tileset = tileset_from_json(PROPERTY_TILESET);
+    let class = tileset.get_tile_class_handle("Floor").unwrap();
+    let floor = tileset.get_tile_handles().find(|handle| tileset.get_class_from_tile(handle) == Some(class)).unwrap();
+
+    assert!(tileset.get_properties(&floor).unwrap().is_empty());
+}
+
+#[test]
+fn field_cells_expose_properties() {
+    let mut field = WaveFunctionField::new_with_seed(load_tileset("simple_area.json"), 3);
+    field.add_sector(0, 0);
+
+    for cell_y in 0..16 {
+        for cell_x in 0..16 {
+            assert_eq!(field.get_cell_property(cell_x, cell_y, "walkable"), Some(&TileProperty::Bool(true)));
+        }
+    }
+
+    // Cells outside the loaded sectors have no tile to ask
+    assert_eq!(field.get_cell_property(16, 0, "walkable"), None);
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::load_tileset;
+use wfc::{field::WaveFunctionField, tileset::TilesetData};
+
+const BROKEN_TILESET: &str = r#"{
+    "tiles": [
+        {"id": "Floor", "texture_id": "floor.png", "edge_ids": ["a", "a", "a", "a"], "can_rotate": false},
+        {"id": "Pillar", "texture_id": "pillar.png", "edge_ids": ["a", "b", "a"], "can_rotate": false},
+        {"id": "Door", "texture_id": "door.png", "edge_ids": ["a", "a", "a", "hinge"], "can_rotate": false}
+    ],
+    "rules": [["a", "a"], ["a", "c"]],
+    "weights": [["Floor", 1], ["Pillar", -1], ["Window", 1]]
+}"#;
+
+#[test]
+fn validation_reports_every_problem() {
+    let errors = TilesetData::from_json(BROKEN_TILESET).unwrap().validate();
+
+    let expected = [
+        "Tile Pillar has 3 edges, expected 4",
+        "Tile Door has no weight and will never be picked",
+        "Rule [a, c] refers to unknown edge c",
+        "Edge b isn't in any rule, tiles using it can never be placed",
+        "Edge hinge isn't in any rule, tiles using it can never be placed",
+        "Weight refers to unknown tile Window",
+        "Tile Pillar has an invalid weight of -1",
+    ];
+
+    for message in expected {
+        assert!(errors.iter().any(|error| error == message), "missing \"{}\" in {:?}", message, errors);
+    }
+    assert_eq!(errors.len(), expected.len(), "{:?}", errors);
+}
+
+#[test]
+fn arcade_tileset_is_valid() {
+    let path = format!("{}/../../assets/arcade_tiles/simple_area.json", env!("CARGO_MANIFEST_DIR"));
+    let data = TilesetData::from_json(&std::fs::read_to_string(path).unwrap()).unwrap();
+
+    assert_eq!(data.validate(), Vec::<String>::new());
+}
+
+#[test]
+fn reload_regenerates_loaded_sectors_from_the_seed() {
+    let mut field = WaveFunctionField::new_with_seed(load_tileset("simple_area.json"), 11);
+    field.stream_around(0, 0);
+    field.wait_for_pending();
+
+    field.set_tileset(load_tileset("simple_area.json"));
+    assert_eq!(field.get_stats().loaded_sectors, 0);
+    field.wait_for_pending();
+
+    let mut fresh = WaveFunctionField::new_with_seed(load_tileset("simple_area.json"), 11);
+    for y in -1..=1 {
+        for x in -1..=1 {
+            fresh.add_sector(x, y);
+        }
+    }
+
+    for cell_y in -16..32 {
+        for cell_x in -16..32 {
+            assert_eq!(field.get_cell(cell_x, cell_y), fresh.get_cell(cell_x, cell_y));
+        }
+    }
+}
+
+#[test]
+fn reload_keeps_modified_tiles() {
+    let mut field = WaveFunctionField::new_with_seed(load_tileset("simple_area.json"), 5);
+    field.add_sector(0, 0);
+    field.add_sector(3, 0);
+
+    let cross = field.get_tileset().find_tile("Path_Cross", 0).unwrap();
+    assert!(field.set_cell(2, 2, cross));
+    assert!(field.set_cell(50, 2, cross));
+    field.evict_sector(3, 0);
+
+    field.set_tileset(load_tileset("simple_area.json"));
+    field.wait_for_pending();
+
+    let cross = field.get_tileset().find_tile("Path_Cross", 0).unwrap();
+    assert_eq!(field.get_cell(2, 2), Some(cross));
+
+    field.add_sector(3, 0);
+    assert_eq!(field.get_cell(50, 2), Some(cross));
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::tileset_from_json;
+use wfc::tileset::{WaveFunctionTileHandle, WaveFunctionTileset};
+
+const ROTATION_TILESET: &str = r#"{
+    "tiles": [
+        {"id": "Spinner", "texture_id": "spinner.png", "edge_ids": ["n", "e", "s", "w"], "can_rotate": true},
+        {"id": "Fixed", "texture_id": "fixed.png", "edge_ids": ["n", "e", "s", "w"], "can_rotate": false}
+    ],
+    "rules": [["n", "s"], ["e", "w"]],
+    "weights": [["Spinner", 1], ["Fixed", 1]]
+}"#;
+
+fn tiles_for_class(tileset: &WaveFunctionTileset, id: &str) -> Vec<WaveFunctionTileHandle> {
+    let class = tileset.get_tile_class_handle(id).unwrap();
+    tileset.get_tile_handles().filter(|handle| tileset.get_class_from_tile(handle) == Some(class)).collect()
+}
+
+fn edges_of(tileset: &WaveFunctionTileset, handle: &WaveFunctionTileHandle) -> Vec<String> {
+    let names = ["n", "e", "s", "w"];
+    (0..4).map(|direction| {
+        let edge = tileset.get_edge(handle, direction).unwrap();
+        names.iter().find(|name| tileset.get_edge_handle(name) == Some(edge)).unwrap().to_string()
+    }).collect()
+}
+
+#[test]
+fn rotations_permute_edges_clockwise() {
+    let tileset = tileset_from_json(ROTATION_TILESET);
+    let spinner = tiles_for_class(&tileset, "Spinner");
+
+    assert_eq!(spinner.len(), 4);
+
+    // Each quarter turn clockwise moves every edge one place round, so the left edge ends up on top
+    let expected = [
+        ["n", "e", "s", "w"],
+        ["w", "n", "e", "s"],
+        ["s", "w", "n", "e"],
+        ["e", "s", "w", "n"],
+    ];
+
+    for handle in spinner.iter() {
+        let rotation = tileset.get_rotation(handle).unwrap();
+        assert_eq!(edges_of(&tileset, handle), expected[rotation as usize], "rotation {}", rotation);
+
+        let (texture_id, degrees) = tileset.get_render_data(handle).unwrap();
+        assert_eq!(texture_id, "spinner.png");
+        assert_eq!(degrees, rotation as f32 * 90.0);
+    }
+
+    let mut rotations: Vec<u32> = spinner.iter().map(|handle| tileset.get_rotation(handle).unwrap()).collect();
+    rotations.sort();
+    assert_eq!(rotations, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn fixed_tiles_are_not_rotated() {
+    let tileset = tileset_from_json(ROTATION_TILESET);
+    let fixed = tiles_for_class(&tileset, "Fixed");
+
+    assert_eq!(fixed.len(), 1);
+    assert_eq!(tileset.get_rotation(&fixed[0]), Some(0));
+    assert_eq!(edges_of(&tileset, &fixed[0]), ["n", "e", "s", "w"]);
+    assert_eq!(tileset.get_tile_count(), 5);
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::load_tileset;
+use wfc::{field::WaveFunctionSector, tileset::WaveFunctionTileHandle};
+
+fn get_tiles(sector: &WaveFunctionSector) -> Vec<Option<WaveFunctionTileHandle>> {
+    (0..sector.get_height()).flat_map(|y| (0..sector.get_width()).map(move |x| sector.get_tile(x, y))).collect()
+}
+
+#[test]
+fn set_tile_ignores_cells_outside_the_sector() {
+    let tileset = load_tileset("simple_area.json");
+    let mut sector = WaveFunctionSector::generate(&tileset, 3, 0, 0, 8, 4);
+    let before = get_tiles(&sector);
+    let tile = sector.get_tile(0, 0).unwrap();
+
+    // Past the end of a row would otherwise land on the start of the next one
+    assert!(!sector.set_tile(8, 0, tile));
+    assert!(!sector.set_tile(0, 4, tile));
+
+    assert_eq!(get_tiles(&sector), before);
+    assert!(!sector.is_modified());
+
+    assert!(sector.set_tile(7, 3, tile));
+    assert_eq!(sector.get_tile(7, 3), Some(tile));
+    assert!(sector.is_modified());
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::tileset_from_json;
+use wfc::field::WaveFunctionSector;
+
+// Every edge matches every other edge, so the weights are the only thing deciding which tile is picked
+const UNCONSTRAINED_TILESET: &str = r#"{
+    "tiles": [
+        {"id": "A", "texture_id": "a.png", "edge_ids": ["any", "any", "any", "any"], "can_rotate": false},
+        {"id": "B", "texture_id": "b.png", "edge_ids": ["any", "any", "any", "any"], "can_rotate": false},
+        {"id": "C", "texture_id": "c.png", "edge_ids": ["any", "any", "any", "any"], "can_rotate": false}
+    ],
+    "rules": [["any", "any"]],
+    "weights": [["A", 1], ["B", 3], ["C", 6]]
+}"#;
+
+const TOLERANCE: f32 = 0.02;
+
+#[test]
+fn class_frequencies_converge_to_weights() {
+    let tileset = tileset_from_json(UNCONSTRAINED_TILESET);
+
+    let classes = ["A", "B", "C"].map(|id| tileset.get_tile_class_handle(id).unwrap());
+    let expected = [0.1, 0.3, 0.6];
+    let mut counts = [0usize; 3];
+    let mut total = 0;
+
+    for seed in 0..8 {
+        for sector_x in 0..5 {
+            let sector = WaveFunctionSector::generate(&tileset, seed, sector_x, 0, 16, 16);
+
+            for y in 0..16 {
+                for x in 0..16 {
+                    let class = tileset.get_class_from_tile(&sector.get_tile(x, y).unwrap()).unwrap();
+                    let index = classes.iter().position(|handle| *handle == class).unwrap();
+                    counts[index] += 1;
+                    total += 1;
+                }
+            }
+        }
+    }
+
+    for (index, count) in counts.iter().enumerate() {
+        let frequency = *count as f32 / total as f32;
+        assert!(
+            (frequency - expected[index]).abs() < TOLERANCE,
+            "class {} appeared {:.3} of the time, expected {:.3}", index, frequency, expected[index]
+        );
+    }
+}